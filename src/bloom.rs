@@ -0,0 +1,394 @@
+use std::borrow::Cow;
+
+use wgpu::util::DeviceExt;
+
+/// Format the scene is drawn in, so colours can go past 1 before tonemapping
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// How strongly bright and newborn cells glow.
+#[derive(Clone, Copy, Debug)]
+pub struct BloomSettings {
+    /// Scene brightness above which pixels glow, on top of the emissive channel
+    pub threshold: f32,
+    /// Strength of the glow, 0 for none
+    pub intensity: f32,
+    /// Compress bright colours with a filmic curve instead of clipping them,
+    /// which shifts every colour a little
+    pub tonemap: bool,
+}
+
+// Layout must match `Bloom` in bloom.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    intensity: f32,
+    tonemap: u32,
+    _padding: u32,
+}
+
+/// Colour targets for pipelines drawn in the scene pass, the HDR colour and
+/// then the emissive channel, which is only written when `emits` is set.
+pub fn scene_targets(blend: wgpu::BlendState, emits: bool) -> [wgpu::ColorTargetState; 2] {
+    [
+        wgpu::ColorTargetState {
+            format: HDR_FORMAT,
+            blend: Some(blend),
+            write_mask: wgpu::ColorWrites::ALL,
+        },
+        wgpu::ColorTargetState {
+            format: HDR_FORMAT,
+            blend: None,
+            write_mask: if emits {
+                wgpu::ColorWrites::ALL
+            } else {
+                wgpu::ColorWrites::empty()
+            },
+        },
+    ]
+}
+
+/// Screen sized targets the scene is drawn into and the half sized ones the
+/// glow is blurred in.
+struct Targets {
+    hdr: wgpu::TextureView,
+    emissive: wgpu::TextureView,
+    bright_bind_group: wgpu::BindGroup,
+    blur_horizontal_bind_group: wgpu::BindGroup,
+    blur_vertical_bind_group: wgpu::BindGroup,
+    tonemap_bind_group: wgpu::BindGroup,
+    // Ping-ponged between by the blur passes, ending up back in the first
+    glow: [wgpu::TextureView; 2],
+    // Drawn into with MSAA, then resolved into `hdr` and `emissive`
+    multisampled: Option<[wgpu::TextureView; 2]>,
+    size: (u32, u32),
+}
+
+/// HDR scene targets with an emissive channel, a threshold and separable
+/// blur bloom, then tonemapping into the SMAA input.
+pub struct Bloom {
+    bright_pipeline: wgpu::RenderPipeline,
+    blur_horizontal_pipeline: wgpu::RenderPipeline,
+    blur_vertical_pipeline: wgpu::RenderPipeline,
+    tonemap_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    sample_count: u32,
+    targets: Targets,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        settings: BloomSettings,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("bloom.wgsl"))),
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Buffer"),
+            contents: bytemuck::cast_slice(&[BloomUniform {
+                threshold: settings.threshold,
+                intensity: settings.intensity,
+                tonemap: settings.tonemap as u32,
+                _padding: 0,
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("bloom_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label, entry_point, format: wgpu::TextureFormat| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[format.into()],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let bright_pipeline = create_pipeline("Bright Pipeline", "fs_bright", HDR_FORMAT);
+        let blur_horizontal_pipeline =
+            create_pipeline("Blur Horizontal Pipeline", "fs_blur_horizontal", HDR_FORMAT);
+        let blur_vertical_pipeline =
+            create_pipeline("Blur Vertical Pipeline", "fs_blur_vertical", HDR_FORMAT);
+        let tonemap_pipeline = create_pipeline("Tonemap Pipeline", "fs_tonemap", format);
+
+        let targets = Self::create_targets(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &uniform_buffer,
+            width,
+            height,
+            sample_count,
+        );
+        Self {
+            bright_pipeline,
+            blur_horizontal_pipeline,
+            blur_vertical_pipeline,
+            tonemap_pipeline,
+            bind_group_layout,
+            sampler,
+            uniform_buffer,
+            sample_count,
+            targets,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Targets {
+        let create_texture_view = |width, height, sample_count, usage, label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let create_view = |width, height, label| {
+            create_texture_view(
+                width,
+                height,
+                1,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                label,
+            )
+        };
+        let hdr = create_view(width, height, "HDR Texture");
+        let emissive = create_view(width, height, "Emissive Texture");
+        // Blurred at half size, which also widens the glow for free
+        let (glow_width, glow_height) = ((width / 2).max(1), (height / 2).max(1));
+        let glow = [
+            create_view(glow_width, glow_height, "Glow Texture"),
+            create_view(glow_width, glow_height, "Glow Texture"),
+        ];
+        let multisampled = (sample_count > 1).then(|| {
+            let create_multisampled_view = |label| {
+                create_texture_view(
+                    width,
+                    height,
+                    sample_count,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    label,
+                )
+            };
+            [
+                create_multisampled_view("Multisampled HDR Texture"),
+                create_multisampled_view("Multisampled Emissive Texture"),
+            ]
+        });
+
+        let create_bind_group = |first, second| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(first),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(second),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("bloom_bind_group"),
+            })
+        };
+        // The blurs only read the first texture
+        Targets {
+            bright_bind_group: create_bind_group(&hdr, &emissive),
+            blur_horizontal_bind_group: create_bind_group(&glow[0], &glow[0]),
+            blur_vertical_bind_group: create_bind_group(&glow[1], &glow[1]),
+            tonemap_bind_group: create_bind_group(&hdr, &glow[0]),
+            hdr,
+            emissive,
+            glow,
+            multisampled,
+            size: (width, height),
+        }
+    }
+
+    /// Matches the targets to the size of the frame about to be drawn.
+    pub fn prepare(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if self.targets.size != (width, height) {
+            self.targets = Self::create_targets(
+                device,
+                &self.bind_group_layout,
+                &self.sampler,
+                &self.uniform_buffer,
+                width,
+                height,
+                self.sample_count,
+            );
+        }
+    }
+
+    /// Attachments for the scene pass, cleared to `background` with nothing emitted.
+    pub fn scene_attachments(
+        &self,
+        background: wgpu::Color,
+    ) -> [wgpu::RenderPassColorAttachment<'_>; 2] {
+        let targets = &self.targets;
+        let attachment = |index: usize, view, clear| match &targets.multisampled {
+            Some(multisampled) => wgpu::RenderPassColorAttachment {
+                view: &multisampled[index],
+                resolve_target: Some(view),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    // Only the resolved copy is read afterwards
+                    store: false,
+                },
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: true,
+                },
+            },
+        };
+        [
+            attachment(0, &targets.hdr, background),
+            attachment(1, &targets.emissive, wgpu::Color::TRANSPARENT),
+        ]
+    }
+
+    /// Blurs the glow and tonemaps the scene with it into `target`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let targets = &self.targets;
+        let passes = [
+            (
+                "Bright Render Pass",
+                &self.bright_pipeline,
+                &targets.bright_bind_group,
+                &targets.glow[0],
+            ),
+            (
+                "Blur Horizontal Render Pass",
+                &self.blur_horizontal_pipeline,
+                &targets.blur_horizontal_bind_group,
+                &targets.glow[1],
+            ),
+            (
+                "Blur Vertical Render Pass",
+                &self.blur_vertical_pipeline,
+                &targets.blur_vertical_bind_group,
+                &targets.glow[0],
+            ),
+            (
+                "Tonemap Render Pass",
+                &self.tonemap_pipeline,
+                &targets.tonemap_bind_group,
+                target,
+            ),
+        ];
+        for (label, pipeline, bind_group, view) in passes {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Glow around bright and emissive pixels, added to the HDR scene before it's
+// brought down to the SMAA input
+
+struct Bloom {
+    // Scene brightness above which pixels glow too
+    threshold: f32;
+    // Strength of the glow added back over the scene
+    intensity: f32;
+    // Non-zero to use the ACES curve rather than clipping
+    tonemap: u32;
+    padding: u32;
+};
+
+// What each pass reads depends on the bind group, see bloom.rs
+[[group(0), binding(0)]]
+var first_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var second_texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var linear_sampler: sampler;
+[[group(0), binding(3)]]
+var<uniform> bloom: Bloom;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+// One triangle covering the whole screen
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32(vertex_index & 1u) * 4.0 - 1.0, f32(vertex_index >> 1u) * 4.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.uv = position * vec2<f32>(0.5, -0.5) + 0.5;
+    return out;
+}
+
+// Reads the scene and emissive channel at half resolution, keeping what glows
+[[stage(fragment)]]
+fn fs_bright(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(first_texture, linear_sampler, in.uv).rgb;
+    let emissive = textureSample(second_texture, linear_sampler, in.uv).rgb;
+    let bright = max(color - vec3<f32>(bloom.threshold), vec3<f32>(0.0));
+    return vec4<f32>(bright + emissive, 1.0);
+}
+
+// Half of a 9 tap gaussian, the centre tap first
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    let step = direction / vec2<f32>(textureDimensions(first_texture));
+    var color = textureSample(first_texture, linear_sampler, uv).rgb * weights[0];
+    for (var i: i32 = 1; i < 5; i = i + 1) {
+        let offset = step * f32(i);
+        color = color + textureSample(first_texture, linear_sampler, uv + offset).rgb * weights[i];
+        color = color + textureSample(first_texture, linear_sampler, uv - offset).rgb * weights[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_blur_horizontal(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return blur(in.uv, vec2<f32>(1.0, 0.0));
+}
+
+[[stage(fragment)]]
+fn fs_blur_vertical(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return blur(in.uv, vec2<f32>(0.0, 1.0));
+}
+
+// Narkowicz's fit of the ACES filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let mapped = (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+    return clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+[[stage(fragment)]]
+fn fs_tonemap(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let scene = textureSample(first_texture, linear_sampler, in.uv);
+    let glow = textureSample(second_texture, linear_sampler, in.uv).rgb * bloom.intensity;
+    // Glow still shows over a transparent background
+    let alpha = max(scene.a, clamp(max(max(glow.r, glow.g), glow.b), 0.0, 1.0));
+    let color = scene.rgb + glow;
+    if (bloom.tonemap == 0u) {
+        return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), alpha);
+    }
+    return vec4<f32>(aces(color), alpha);
+}
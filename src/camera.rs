@@ -1,4 +1,9 @@
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{
+        ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    },
+};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -8,6 +13,14 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+/// Where the camera is and what it's looking at, used for keyframes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub eye: cgmath::Point3<f32>,
+    pub target: cgmath::Point3<f32>,
+    pub fovy: f32,
+}
+
 pub struct Camera {
     eye: cgmath::Point3<f32>,
     target: cgmath::Point3<f32>,
@@ -16,10 +29,12 @@ pub struct Camera {
     fovy: f32,
     znear: f32,
     zfar: f32,
+    grid_width: f32,
+    orthographic: bool,
 }
 
 impl Camera {
-    pub fn new(config: &wgpu::SurfaceConfiguration, grid_width: u32) -> Self {
+    pub fn new(config: &wgpu::SurfaceConfiguration, grid_width: u32, orthographic: bool) -> Self {
         let double_grid_width = grid_width as f32 * 2.;
         let half_grid_width = grid_width as f32 / 2.;
         Self {
@@ -30,12 +45,79 @@ impl Camera {
             fovy: 45.0,
             znear: 0.1,
             zfar: grid_width as f32 * 4.,
+            grid_width: grid_width as f32,
+            orthographic,
         }
     }
 
-    fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
-        let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    pub fn set_grid_width(&mut self, grid_width: u32) {
+        self.grid_width = grid_width as f32;
+    }
+
+    pub fn toggle_orthographic(&mut self) {
+        self.orthographic = !self.orthographic;
+    }
+
+    pub fn view(&self) -> View {
+        View {
+            eye: self.eye,
+            target: self.target,
+            fovy: self.fovy,
+        }
+    }
+
+    pub fn set_view(&mut self, view: &View) {
+        self.eye = view.eye;
+        self.target = view.target;
+        self.fovy = view.fovy;
+        self.fit_far_plane();
+    }
+
+    /// Pushes the far plane out just past the furthest corner of the grid,
+    /// wherever the eye has moved to.
+    fn fit_far_plane(&mut self) {
+        use cgmath::InnerSpace;
+        let furthest = (0..8)
+            .map(|corner| {
+                let corner = cgmath::Point3::new(
+                    (corner & 1) as f32,
+                    ((corner >> 1) & 1) as f32,
+                    ((corner >> 2) & 1) as f32,
+                ) * self.grid_width;
+                (corner - self.eye).magnitude()
+            })
+            .fold(0., f32::max);
+        self.zfar = furthest + 1.;
+    }
+
+    pub fn build_view_matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        let view = self.build_view_matrix();
+        let proj = if self.orthographic {
+            use cgmath::InnerSpace;
+            // Same size as the perspective view at the target, so zooming
+            // in and out still scales the view
+            let half_height = (self.target - self.eye).magnitude()
+                * cgmath::Rad::from(cgmath::Deg(self.fovy / 2.)).0.tan();
+            let half_width = half_height * self.aspect;
+            cgmath::ortho(
+                -half_width,
+                half_width,
+                -half_height,
+                half_height,
+                self.znear,
+                self.zfar,
+            )
+        } else {
+            cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar)
+        };
         OPENGL_TO_WGPU_MATRIX * proj * view
     }
 }
@@ -48,6 +130,9 @@ pub struct CameraUniform {
     // We can't use cgmath with bytemuck directly so we'll have
     // to convert the Matrix4 into a 4x4 f32 array
     view_proj: [[f32; 4]; 4],
+    // Screen axes in the world, for sprites that face the camera
+    right: [f32; 4],
+    up: [f32; 4],
 }
 
 impl CameraUniform {
@@ -55,33 +140,273 @@ impl CameraUniform {
         use cgmath::SquareMatrix;
         Self {
             view_proj: cgmath::Matrix4::identity().into(),
+            right: [1., 0., 0., 0.],
+            up: [0., 1., 0., 0.],
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
+        use cgmath::InnerSpace;
         self.view_proj = camera.build_view_projection_matrix().into();
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        self.right = right.extend(0.).into();
+        self.up = up.extend(0.).into();
     }
 }
 
+/// Eye position described relative to the target, which is what gets eased
+/// towards when the camera moves.
+#[derive(Clone, Copy, Debug)]
+struct Orbit {
+    target: cgmath::Point3<f32>,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+}
+
+impl Orbit {
+    fn from_camera(camera: &Camera) -> Self {
+        use cgmath::InnerSpace;
+        let offset = camera.eye - camera.target;
+        let distance = offset.magnitude();
+        Self {
+            target: camera.target,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).asin(),
+            distance,
+        }
+    }
+
+    fn eye(&self) -> cgmath::Point3<f32> {
+        self.target + direction(self.yaw, self.pitch) * self.distance
+    }
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        use cgmath::EuclideanSpace;
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            target: cgmath::Point3::from_vec(
+                self.target.to_vec() + (other.target - self.target) * t,
+            ),
+            yaw: lerp(self.yaw, other.yaw),
+            pitch: lerp(self.pitch, other.pitch),
+            distance: lerp(self.distance, other.distance),
+        }
+    }
+}
+
+/// First person view, with yaw and pitch giving the look direction. The
+/// target is kept `distance` ahead, which sets the orthographic view size.
+#[derive(Clone, Copy, Debug)]
+struct Flight {
+    eye: cgmath::Point3<f32>,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+}
+
+impl Flight {
+    fn from_camera(camera: &Camera) -> Self {
+        use cgmath::InnerSpace;
+        let offset = camera.target - camera.eye;
+        let forward = offset.normalize();
+        Self {
+            eye: camera.eye,
+            yaw: forward.x.atan2(forward.z),
+            pitch: forward.y.asin(),
+            distance: offset.magnitude(),
+        }
+    }
+
+    fn forward(&self) -> cgmath::Vector3<f32> {
+        direction(self.yaw, self.pitch)
+    }
+}
+
+/// Unit vector pointing along the given yaw around y and pitch up from the xz plane.
+fn direction(yaw: f32, pitch: f32) -> cgmath::Vector3<f32> {
+    cgmath::Vector3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    )
+}
+
+/// Slow automatic orbit around the centre of the grid, for demos.
+#[derive(Clone, Copy, Debug)]
+pub struct Turntable {
+    pub enabled: bool,
+    /// Radians per second
+    pub speed: f32,
+    /// Radians above the horizon
+    pub elevation: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewPreset {
+    Front,
+    Top,
+    Side,
+    Isometric,
+}
+
+impl ViewPreset {
+    fn yaw_pitch(self) -> (f32, f32) {
+        use std::f32::consts::FRAC_PI_4;
+        match self {
+            ViewPreset::Front => (0., 0.),
+            ViewPreset::Top => (0., MAX_PITCH),
+            ViewPreset::Side => (2. * FRAC_PI_4, 0.),
+            ViewPreset::Isometric => (FRAC_PI_4, (1. / 2f32.sqrt()).atan()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    Orbit,
+    Fly,
+}
+
+// Stops the camera flipping over the top and bottom
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 * 0.98;
+const MIN_DISTANCE: f32 = 1.;
+// Furthest the orbit can zoom out, in grid widths
+const MAX_DISTANCE: f32 = 4.;
+// Radians per pixel dragged
+const ORBIT_SENSITIVITY: f32 = 0.005;
+// Fraction of the orbit distance per pixel dragged
+const PAN_SENSITIVITY: f32 = 0.0015;
+// Distance is scaled by this per scroll wheel line
+const ZOOM_STEP: f32 = 0.9;
+// Higher eases into the goal orbit faster
+const SMOOTHING: f32 = 12.;
+// Radians per unit of raw mouse motion
+const LOOK_SENSITIVITY: f32 = 0.002;
+// Flying speed in grid widths per second, and how much shift multiplies it
+const FLY_SPEED: f32 = 0.25;
+const BOOST: f32 = 4.;
+
 pub struct CameraController {
+    // Keyboard orbit in radians per second
     speed: f32,
+    mode: CameraMode,
     is_forward_pressed: bool,
     is_backward_pressed: bool,
     is_left_pressed: bool,
     is_right_pressed: bool,
+    is_up_pressed: bool,
+    is_down_pressed: bool,
+    is_boost_pressed: bool,
+    is_orbiting: bool,
+    is_panning: bool,
+    last_cursor: Option<PhysicalPosition<f64>>,
+    current: Orbit,
+    goal: Orbit,
+    flight: Flight,
+    // Scroll wheel adjusts this multiplier on the flying speed
+    fly_speed: f32,
+    turntable: Turntable,
 }
 
 impl CameraController {
-    pub fn new(speed: f32) -> Self {
+    pub fn new(speed: f32, camera: &Camera, turntable: Turntable) -> Self {
+        let orbit = Orbit::from_camera(camera);
         Self {
             speed,
+            mode: CameraMode::Orbit,
             is_forward_pressed: false,
             is_backward_pressed: false,
             is_left_pressed: false,
             is_right_pressed: false,
+            is_up_pressed: false,
+            is_down_pressed: false,
+            is_boost_pressed: false,
+            is_orbiting: false,
+            is_panning: false,
+            last_cursor: None,
+            current: orbit,
+            goal: orbit,
+            flight: Flight::from_camera(camera),
+            fly_speed: 1.,
+            turntable,
         }
     }
 
+    /// Picks up from the camera's current view, after something else has moved it.
+    pub fn reset_to(&mut self, camera: &Camera) {
+        self.current = Orbit::from_camera(camera);
+        self.goal = self.current;
+        self.flight = Flight::from_camera(camera);
+    }
+
+    pub fn toggle_turntable(&mut self, camera: &Camera) {
+        self.orbit_mode(camera);
+        self.turntable.enabled = !self.turntable.enabled;
+    }
+
+    /// Eases the orbit around to look at the whole grid from a preset angle.
+    pub fn view_preset(&mut self, camera: &Camera, preset: ViewPreset) {
+        let grid_min = cgmath::Point3::new(0., 0., 0.);
+        let grid_max = grid_min + cgmath::Vector3::new(1., 1., 1.) * camera.grid_width;
+        self.frame(camera, grid_min, grid_max);
+
+        let (yaw, pitch) = preset.yaw_pitch();
+        // Take the short way round, the yaw may have wound up many turns
+        let turns = ((self.goal.yaw - yaw) / std::f32::consts::TAU).round();
+        self.goal.yaw = yaw + turns * std::f32::consts::TAU;
+        self.goal.pitch = pitch;
+    }
+
+    /// Eases the orbit to fit the box from `min` to `max` in view, keeping the angle.
+    pub fn frame(&mut self, camera: &Camera, min: cgmath::Point3<f32>, max: cgmath::Point3<f32>) {
+        use cgmath::{EuclideanSpace, InnerSpace};
+        self.orbit_mode(camera);
+        self.turntable.enabled = false;
+
+        let radius = (max - min).magnitude() / 2.;
+        let half_fovy = cgmath::Rad::from(cgmath::Deg(camera.fovy)).0 / 2.;
+        let half_fovx = (half_fovy.tan() * camera.aspect).atan();
+        self.goal.target = cgmath::Point3::from_vec((min.to_vec() + max.to_vec()) / 2.);
+        self.goal.distance = radius / half_fovy.min(half_fovx).sin();
+    }
+
+    fn orbit_mode(&mut self, camera: &Camera) {
+        if self.mode == CameraMode::Fly {
+            self.toggle_mode(camera);
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switches between orbiting and flying, starting from the current view.
+    pub fn toggle_mode(&mut self, camera: &Camera) {
+        self.mode = match self.mode {
+            CameraMode::Orbit => {
+                self.flight = Flight::from_camera(camera);
+                CameraMode::Fly
+            }
+            CameraMode::Fly => {
+                // Orbit around the point the camera was looking at
+                let orbit = Orbit {
+                    target: self.flight.eye + self.flight.forward() * self.flight.distance,
+                    yaw: self.flight.yaw + std::f32::consts::PI,
+                    pitch: -self.flight.pitch,
+                    distance: self.flight.distance,
+                };
+                self.current = orbit;
+                self.goal = orbit;
+                CameraMode::Orbit
+            }
+        };
+        self.is_orbiting = false;
+        self.is_panning = false;
+    }
+
     pub fn process_events(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
@@ -111,42 +436,154 @@ impl CameraController {
                         self.is_right_pressed = is_pressed;
                         true
                     }
+                    VirtualKeyCode::E => {
+                        self.is_up_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::Q => {
+                        self.is_down_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::LShift | VirtualKeyCode::RShift => {
+                        self.is_boost_pressed = is_pressed;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } if self.mode == CameraMode::Orbit => {
+                let is_pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Left => {
+                        self.is_orbiting = is_pressed;
+                        self.turntable.enabled &= !is_pressed;
+                        true
+                    }
+                    MouseButton::Right | MouseButton::Middle => {
+                        self.is_panning = is_pressed;
+                        self.turntable.enabled &= !is_pressed;
+                        true
+                    }
                     _ => false,
                 }
             }
+            WindowEvent::CursorMoved { position, .. } => {
+                let last_cursor = self.last_cursor.replace(*position);
+                let (dx, dy) = match last_cursor {
+                    Some(last) => ((position.x - last.x) as f32, (position.y - last.y) as f32),
+                    None => return false,
+                };
+
+                if self.is_orbiting {
+                    self.goal.yaw -= dx * ORBIT_SENSITIVITY;
+                    self.goal.pitch =
+                        (self.goal.pitch + dy * ORBIT_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+                }
+                if self.is_panning {
+                    use cgmath::InnerSpace;
+                    let forward = (self.goal.target - self.goal.eye()).normalize();
+                    let right = forward.cross(cgmath::Vector3::unit_y()).normalize();
+                    let up = right.cross(forward);
+                    // Drags the grid along with the cursor
+                    self.goal.target +=
+                        (up * dy - right * dx) * self.goal.distance * PAN_SENSITIVITY;
+                }
+                self.is_orbiting || self.is_panning
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.,
+                };
+                match self.mode {
+                    CameraMode::Orbit => self.goal.distance *= ZOOM_STEP.powf(lines),
+                    CameraMode::Fly => self.fly_speed /= ZOOM_STEP.powf(lines),
+                }
+                true
+            }
             _ => false,
         }
     }
 
-    pub fn update_camera(&self, camera: &mut Camera) {
-        use cgmath::InnerSpace;
-        let forward = camera.target - camera.eye;
-        let forward_norm = forward.normalize();
-        let forward_mag = forward.magnitude();
+    /// Mouse look while flying, takes raw mouse motion since the cursor is grabbed.
+    pub fn process_mouse_motion(&mut self, (dx, dy): (f64, f64)) {
+        if self.mode == CameraMode::Fly {
+            self.flight.yaw -= dx as f32 * LOOK_SENSITIVITY;
+            self.flight.pitch =
+                (self.flight.pitch - dy as f32 * LOOK_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+    }
 
-        // Prevents glitching when camera gets too close to the
-        // center of the scene.
-        if self.is_forward_pressed && forward_mag > self.speed {
-            camera.eye += forward_norm * self.speed;
+    /// Moves the camera, `dt` is the frame time in seconds.
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        match self.mode {
+            CameraMode::Orbit => self.update_orbit(camera, dt),
+            CameraMode::Fly => self.update_flight(camera, dt),
+        }
+        camera.fit_far_plane();
+    }
+
+    fn update_orbit(&mut self, camera: &mut Camera, dt: f32) {
+        if self.is_right_pressed {
+            self.goal.yaw += self.speed * dt;
+        }
+        if self.is_left_pressed {
+            self.goal.yaw -= self.speed * dt;
+        }
+        if self.is_forward_pressed {
+            self.goal.distance *= (-self.speed * dt).exp();
         }
         if self.is_backward_pressed {
-            camera.eye -= forward_norm * self.speed;
+            self.goal.distance *= (self.speed * dt).exp();
         }
 
-        let right = forward_norm.cross(camera.up);
+        if self.turntable.enabled {
+            let half_width = camera.grid_width / 2.;
+            self.goal.target = cgmath::Point3::new(half_width, half_width, half_width);
+            self.goal.pitch = self.turntable.elevation;
+            self.goal.yaw += self.turntable.speed * dt;
+        }
 
-        // Redo radius calc in case the fowrard/backward is pressed.
-        let forward = camera.target - camera.eye;
-        let forward_mag = forward.magnitude();
+        self.goal.distance = self
+            .goal
+            .distance
+            .clamp(MIN_DISTANCE, camera.grid_width * MAX_DISTANCE);
 
-        if self.is_right_pressed {
-            // Rescale the distance between the target and eye so
-            // that it doesn't change. The eye therefore still
-            // lies on the circle made by the target and eye.
-            camera.eye = camera.target - (forward + right * self.speed).normalize() * forward_mag;
+        // Exponential easing so the motion is the same at any frame rate
+        self.current = self.current.lerp(&self.goal, 1. - (-SMOOTHING * dt).exp());
+        camera.target = self.current.target;
+        camera.eye = self.current.eye();
+    }
+
+    fn update_flight(&mut self, camera: &mut Camera, dt: f32) {
+        use cgmath::InnerSpace;
+        let forward = self.flight.forward();
+        let right = forward.cross(cgmath::Vector3::unit_y()).normalize();
+        let up = cgmath::Vector3::unit_y();
+
+        let mut velocity = cgmath::Vector3::new(0., 0., 0.);
+        let axes = [
+            (self.is_forward_pressed, forward),
+            (self.is_backward_pressed, -forward),
+            (self.is_right_pressed, right),
+            (self.is_left_pressed, -right),
+            (self.is_up_pressed, up),
+            (self.is_down_pressed, -up),
+        ];
+        for (is_pressed, axis) in axes {
+            if is_pressed {
+                velocity += axis;
+            }
         }
-        if self.is_left_pressed {
-            camera.eye = camera.target - (forward - right * self.speed).normalize() * forward_mag;
+        if velocity.magnitude2() > 0. {
+            let mut speed = camera.grid_width * FLY_SPEED * self.fly_speed;
+            if self.is_boost_pressed {
+                speed *= BOOST;
+            }
+            self.flight.eye += velocity.normalize() * speed * dt;
         }
+
+        camera.eye = self.flight.eye;
+        camera.target = self.flight.eye + forward * self.flight.distance;
     }
 }
@@ -0,0 +1,119 @@
+use std::mem;
+
+use nanorand::{Rng, WyRand};
+
+// Layout must match `Cell` in compute.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Cell {
+    pub state: i32,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// Generations since the cell was born, 0 while dead
+    pub age: i32,
+    /// Live neighbours in the generation this cell was computed from
+    pub neighbours: i32,
+}
+
+impl Cell {
+    pub const DEAD: i32 = 0;
+    pub const ALIVE: i32 = 1;
+
+    /// Builds a full grid of dead cells, laid out x-major like the compute shader expects.
+    pub fn new_grid(grid_width: u32) -> Vec<Self> {
+        let mut cells = Vec::with_capacity((grid_width * grid_width * grid_width) as usize);
+        for x in 0..grid_width as i32 {
+            for y in 0..grid_width as i32 {
+                for z in 0..grid_width as i32 {
+                    cells.push(Self {
+                        x,
+                        y,
+                        z,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        cells
+    }
+
+    /// Widest grid whose cells fit in `max_bytes`.
+    pub fn max_grid_width(max_bytes: u64) -> u32 {
+        let max_cells = max_bytes / mem::size_of::<Self>() as u64;
+        let mut width = (max_cells as f64).cbrt() as u64;
+        // The float root can land either side of the exact one
+        while width.pow(3) > max_cells {
+            width -= 1;
+        }
+        while (width + 1).pow(3) <= max_cells {
+            width += 1;
+        }
+        width as u32
+    }
+
+    /// Grid where each cell is alive with probability `density`, the same seed gives the same grid.
+    pub fn random_grid(grid_width: u32, density: f32, seed: u64) -> Vec<Self> {
+        let mut rng = WyRand::new_seed(seed);
+        let mut cells = Self::new_grid(grid_width);
+        for cell in cells.iter_mut() {
+            if rng.generate::<f32>() < density {
+                cell.state = Self::ALIVE;
+            }
+        }
+        cells
+    }
+
+    /// Index of the cell at the given grid coordinates, or None when outside the grid.
+    pub fn index(x: i32, y: i32, z: i32, grid_width: u32) -> Option<usize> {
+        let w = grid_width as i32;
+        if x < 0 || x >= w || y < 0 || y >= w || z < 0 || z >= w {
+            return None;
+        }
+        Some((z + y * w + x * w * w) as usize)
+    }
+
+    /// Anything that isn't dead is drawn.
+    pub fn is_solid(&self) -> bool {
+        self.state != Self::DEAD
+    }
+}
+
+/// Counts for one generation compared with the one before it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Population {
+    pub live: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+impl Population {
+    pub fn count(cells: &[Cell], previous_cells: &[Cell]) -> Self {
+        let mut population = Self::default();
+        for (cell, previous) in cells.iter().zip(previous_cells) {
+            if cell.state == Cell::ALIVE {
+                population.live += 1;
+                if previous.state == Cell::DEAD {
+                    population.births += 1;
+                }
+            } else if cell.state == Cell::DEAD && previous.is_solid() {
+                population.deaths += 1;
+            }
+        }
+        population
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_grid_width_fits_exactly() {
+        let cell = mem::size_of::<Cell>() as u64;
+        assert_eq!(Cell::max_grid_width(64 * 64 * 64 * cell), 64);
+        assert_eq!(Cell::max_grid_width(64 * 64 * 64 * cell - 1), 63);
+        assert_eq!(Cell::max_grid_width(128 << 20), 177);
+        assert_eq!(Cell::max_grid_width(0), 0);
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::camera::ViewPreset;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    pub fn next(self) -> Self {
+        match self {
+            Axis::X => Axis::Y,
+            Axis::Y => Axis::Z,
+            Axis::Z => Axis::X,
+        }
+    }
+
+    /// The preset looking straight down this axis.
+    pub fn view_preset(self) -> ViewPreset {
+        match self {
+            Axis::X => ViewPreset::Side,
+            Axis::Y => ViewPreset::Top,
+            Axis::Z => ViewPreset::Front,
+        }
+    }
+}
+
+/// Axis aligned planes that hide cells past a chosen layer on each axis,
+/// so the inside of dense grids can be seen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clip {
+    /// The axis whose plane is moved with the keys
+    pub axis: Axis,
+    /// Last visible layer along X, Y and Z
+    pub layers: [u32; 3],
+    /// Only show the chosen layer on the current axis, like a 2D automaton
+    pub single_layer: bool,
+    grid_width: u32,
+}
+
+impl Clip {
+    pub fn new(grid_width: u32) -> Self {
+        Self {
+            axis: Axis::Z,
+            layers: [grid_width - 1; 3],
+            single_layer: false,
+            grid_width,
+        }
+    }
+
+    pub fn grid_width(&self) -> u32 {
+        self.grid_width
+    }
+
+    pub fn layer(&self) -> u32 {
+        self.layers[self.axis as usize]
+    }
+
+    pub fn set_layer(&mut self, layer: u32) {
+        self.layers[self.axis as usize] = layer.min(self.grid_width - 1);
+    }
+
+    pub fn move_layer(&mut self, change: i32) {
+        let layer = (self.layer() as i32 + change).max(0);
+        self.set_layer(layer as u32);
+    }
+
+    /// Whether anything is being hidden.
+    pub fn is_active(&self) -> bool {
+        self.single_layer || self.layers.iter().any(|&layer| layer < self.grid_width - 1)
+    }
+
+    pub fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        let position = [x, y, z];
+        let k = self.axis as usize;
+        if self.single_layer {
+            position[k] == self.layers[k] as i32
+        } else {
+            (0..3).all(|k| position[k] <= self.layers[k] as i32)
+        }
+    }
+}
+
+impl fmt::Display for Clip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.single_layer {
+            write!(f, "Layer {:?} = {}", self.axis, self.layer())
+        } else {
+            let planes = Axis::ALL
+                .iter()
+                .filter(|&&axis| self.layers[axis as usize] < self.grid_width - 1)
+                .map(|&axis| format!("{:?} 0-{}", axis, self.layers[axis as usize]))
+                .collect::<Vec<_>>();
+            write!(f, "Clip {}", planes.join(", "))
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+
+// Must match the stops in color.wgsl
+const MAX_STOPS: usize = 8;
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Cell x/y/z mapped to red/green/blue
+    Position,
+    /// Generations since the cell was born
+    Age,
+    /// Live neighbours that decided the cell's state
+    Neighbours,
+    /// Distance from the centre of the grid
+    Distance,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 4] = [
+        ColorMode::Position,
+        ColorMode::Age,
+        ColorMode::Neighbours,
+        ColorMode::Distance,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&mode| mode == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    fn default_palette(self) -> Palette {
+        let hex = match self {
+            // No stops means the position is used as the colour directly
+            ColorMode::Position => "",
+            ColorMode::Age => "#fcffa4,#f98e09,#bc3754,#57106e,#000004",
+            ColorMode::Neighbours => "#440154,#3b528b,#21918c,#5ec962,#fde725",
+            ColorMode::Distance => "#30123b,#4686fb,#1ae4b6,#a2fc3c,#fabb39,#e4460a,#7a0403",
+        };
+        hex.parse().unwrap()
+    }
+}
+
+/// Opacity ramp for the transparent render mode from newborn cells to ones
+/// that have lived for a while, written like "0.6,0.1".
+/// A single value is used for every cell.
+#[derive(Clone, Copy, Debug)]
+pub struct Opacity {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl FromStr for Opacity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once(',').unwrap_or((s, s));
+        let parse = |alpha: &str| {
+            alpha
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|alpha| (0. ..=1.).contains(alpha))
+                .ok_or_else(|| anyhow!("'{}' is not an opacity from 0 to 1", alpha))
+        };
+        Ok(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+/// Gradient of evenly spaced colour stops, written as comma separated hex colours.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    stops: Vec<[f32; 3]>,
+}
+
+impl FromStr for Palette {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stops = s
+            .split(',')
+            .map(str::trim)
+            .filter(|hex| !hex.is_empty())
+            .map(parse_hex)
+            .collect::<Result<Vec<_>, _>>()?;
+        if stops.len() > MAX_STOPS {
+            bail!("palettes can have at most {} colours", MAX_STOPS);
+        }
+        Ok(Self { stops })
+    }
+}
+
+fn parse_hex(hex: &str) -> anyhow::Result<[f32; 3]> {
+    let digits = hex.trim_start_matches('#');
+    let rgb = u32::from_str_radix(digits, 16)
+        .ok()
+        .filter(|_| digits.len() == 6)
+        .ok_or_else(|| anyhow!("'{}' is not a colour like #ff8800", hex))?;
+    Ok([16, 8, 0].map(|shift| ((rgb >> shift) & 0xff) as f32 / 255.))
+}
+
+/// Active colour mode along with a palette for each mode.
+pub struct ColorScheme {
+    pub mode: ColorMode,
+    palettes: Vec<Palette>,
+    pub opacity: Opacity,
+}
+
+impl ColorScheme {
+    /// `palette` replaces the default palette of the starting mode.
+    pub fn new(mode: ColorMode, palette: Option<Palette>, opacity: Opacity) -> Self {
+        let palettes = ColorMode::ALL
+            .iter()
+            .map(|&m| match &palette {
+                Some(palette) if m == mode => palette.clone(),
+                _ => m.default_palette(),
+            })
+            .collect();
+        Self {
+            mode,
+            palettes,
+            opacity,
+        }
+    }
+
+    pub fn to_uniform(&self, grid_width: u32) -> ColorUniform {
+        let i = ColorMode::ALL.iter().position(|&m| m == self.mode).unwrap();
+        let palette = &self.palettes[i];
+        let mut stops = [[0.; 4]; MAX_STOPS];
+        for (stop, color) in stops.iter_mut().zip(&palette.stops) {
+            stop[..3].copy_from_slice(color);
+        }
+        ColorUniform {
+            stops,
+            mode: i as u32,
+            stop_count: palette.stops.len() as u32,
+            grid_width,
+            opacity_start: self.opacity.start,
+            opacity_end: self.opacity.end,
+            _padding: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorUniform {
+    // vec4 rather than vec3 to satisfy uniform array alignment
+    stops: [[f32; 4]; MAX_STOPS],
+    mode: u32,
+    stop_count: u32,
+    grid_width: u32,
+    opacity_start: f32,
+    opacity_end: f32,
+    _padding: [u32; 3],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_ramp_or_a_single_opacity() {
+        let opacity: Opacity = " 0.6 , 0.1 ".parse().unwrap();
+        assert_eq!((opacity.start, opacity.end), (0.6, 0.1));
+        let opacity: Opacity = "1".parse().unwrap();
+        assert_eq!((opacity.start, opacity.end), (1., 1.));
+        let opacity: Opacity = "0,1".parse().unwrap();
+        assert_eq!((opacity.start, opacity.end), (0., 1.));
+    }
+
+    #[test]
+    fn rejects_malformed_opacities() {
+        for s in [
+            "",
+            ",",
+            "0.5,",
+            "1.5",
+            "-0.1,0.5",
+            "0.5,0.1,0.3",
+            "NaN",
+            "half",
+        ] {
+            assert!(s.parse::<Opacity>().is_err(), "{:?} should be rejected", s);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Cell layout and colouring shared by the shaders that draw cells, included
+// ahead of each of them
+
+struct Cell {
+    state      : i32;
+    x          : i32;
+    y          : i32;
+    z          : i32;
+    age        : i32;
+    neighbours : i32;
+};
+
+struct Cells {
+    cells : [[stride(24)]] array<Cell>;
+};
+
+struct ColorScheme {
+    stops: array<vec4<f32>, 8u>;
+    mode: u32;
+    stop_count: u32;
+    grid_width: u32;
+    // Only used by the transparent render mode
+    opacity_start: f32;
+    opacity_end: f32;
+    padding0: u32;
+    padding1: vec2<u32>;
+};
+
+let DEAD_STATE: i32 = 0;
+
+// Order follows ColorMode in color.rs
+let POSITION_MODE: u32 = 0u;
+let AGE_MODE: u32 = 1u;
+let NEIGHBOURS_MODE: u32 = 2u;
+
+// Age at which the end of the palette and opacity ramp is reached
+let AGE_RANGE: f32 = 32.0;
+
+// From 0 for newborn cells to 1 at AGE_RANGE, so colour and opacity by age
+// reach their ends together
+fn age_fraction(cell: Cell) -> f32 {
+    return clamp(f32(cell.age) / AGE_RANGE, 0.0, 1.0);
+}
+
+fn sample_palette(colors: ColorScheme, t: f32) -> vec3<f32> {
+    if (colors.stop_count < 2u) {
+        return colors.stops[0].rgb;
+    }
+    // Arrays passed by value can only be indexed by constants
+    var stops = colors.stops;
+    let scaled = clamp(t, 0.0, 1.0) * f32(colors.stop_count - 1u);
+    let i = min(u32(scaled), colors.stop_count - 2u);
+    return mix(stops[i].rgb, stops[i + 1u].rgb, scaled - f32(i));
+}
+
+fn cell_color(colors: ColorScheme, cell: Cell) -> vec3<f32> {
+    let width = f32(colors.grid_width);
+    let position = vec3<f32>(f32(cell.x), f32(cell.y), f32(cell.z));
+
+    if (colors.mode == POSITION_MODE) {
+        if (colors.stop_count == 0u) {
+            return position / width;
+        }
+        return sample_palette(colors, (position.x + position.y + position.z) / (3.0 * (width - 1.0)));
+    } else if (colors.mode == AGE_MODE) {
+        return sample_palette(colors, age_fraction(cell));
+    } else if (colors.mode == NEIGHBOURS_MODE) {
+        return sample_palette(colors, f32(cell.neighbours) / 26.0);
+    }
+
+    // Distance from the centre, 1.0 at the corners of the grid
+    let offset = position + 0.5 - width / 2.0;
+    return sample_palette(colors, length(offset) / (sqrt(3.0) * width / 2.0));
+}
+
+fn cell_opacity(colors: ColorScheme, cell: Cell) -> f32 {
+    return mix(colors.opacity_start, colors.opacity_end, age_fraction(cell));
+}
+
+// Generations a newborn cell glows for, fading as it ages
+let GLOW_GENERATIONS: f32 = 4.0;
+
+// How brightly a cell lights up the emissive channel, from 0 to 1
+fn cell_glow(cell: Cell) -> f32 {
+    return clamp(1.0 - f32(cell.age) / GLOW_GENERATIONS, 0.0, 1.0);
+}
+
+// Colour and glow written by the pipelines that light up the bloom
+struct SceneOutput {
+    [[location(0)]] color: vec4<f32>;
+    [[location(1)]] emissive: vec4<f32>;
+};
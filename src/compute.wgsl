@@ -1,17 +1,20 @@
 struct Ruleset {
   ruleset : array<u32, 27u>;
   grid_width: u32;
+  boundary: u32;
 };
 
 struct Cell {
-  state : i32;
-  x     : i32;
-  y     : i32;
-  z     : i32;
+  state      : i32;
+  x          : i32;
+  y          : i32;
+  z          : i32;
+  age        : i32;
+  neighbours : i32;
 };
 
 struct Cells {
-  cells : [[stride(16)]] array<Cell>;
+  cells : [[stride(24)]] array<Cell>;
 };
 
 let DEAD_STATE: i32 = 0;
@@ -21,18 +24,28 @@ let DEATH_RULE = 0u;
 let SURVIVE_RULE = 1u;
 let BIRTH_RULE = 2u;
 
+// Order follows Boundary in rule.rs
+let WRAP_BOUNDARY = 1u;
+
 
 [[group(0), binding(0)]] var<uniform> ruleset : Ruleset;
 [[group(0), binding(1)]] var<storage, read> cellsSrc : Cells;
 [[group(0), binding(2)]] var<storage, read_write> cellsDst : Cells;
 
+// Invocations in a row of workgroups, big grids are dispatched over several rows
+let ROW_INVOCATIONS: u32 = 16776960u; // 65535 * 256
+
 [[stage(compute), workgroup_size(256)]]
 fn main([[builtin(global_invocation_id)]] global_invocation_id: vec3<u32>) {
-  let index = global_invocation_id.x;
+  let index = global_invocation_id.x + global_invocation_id.y * ROW_INVOCATIONS;
+  if (index >= arrayLength(&cellsSrc.cells)) {
+    return;
+  }
+
   var neighbour_count = 0;
 
   // TODO: Is casting expensive?? Should really just use an encase or crevise
-  let grid_width = i32(ruleset.grid_width); 
+  let grid_width = i32(ruleset.grid_width);
 
   let cell = cellsSrc.cells[index];
   for (var dx = -1; dx < 2; dx = dx + 1) {
@@ -43,30 +56,41 @@ fn main([[builtin(global_invocation_id)]] global_invocation_id: vec3<u32>) {
         }
 
         // Getting candidate neighbor
-        let nx = cell.x + dx;
-        let ny = cell.y + dy;
-        let nz = cell.z + dz;
+        var nx = cell.x + dx;
+        var ny = cell.y + dy;
+        var nz = cell.z + dz;
 
-        // Checking bounds of the grid
-        if (nx < 0 || nx > grid_width - 1 || ny < 0 || ny > grid_width - 1 || nz < 0 || nz > grid_width - 1) {
+        if (ruleset.boundary == WRAP_BOUNDARY) {
+          nx = (nx + grid_width) % grid_width;
+          ny = (ny + grid_width) % grid_width;
+          nz = (nz + grid_width) % grid_width;
+        } else if (nx < 0 || nx > grid_width - 1 || ny < 0 || ny > grid_width - 1 || nz < 0 || nz > grid_width - 1) {
+          // Outside the grid counts as dead
           continue;
         }
 
         let neighbour_state = cellsSrc.cells[nz + (ny * grid_width) + (nx * grid_width * grid_width)].state;
         if (neighbour_state == ALIVE_STATE) {
           neighbour_count = neighbour_count + 1;
-        } 
+        }
       }
     }
   }
 
 
   let rule = ruleset.ruleset[neighbour_count];
+  var next_state = DEAD_STATE;
   if (rule == SURVIVE_RULE && cell.state == ALIVE_STATE) {
-    cellsDst.cells[index].state = DEAD_STATE;
+    next_state = DEAD_STATE;
   } else if (rule == BIRTH_RULE && cell.state == DEAD_STATE) {
-    cellsDst.cells[index].state = ALIVE_STATE;
+    next_state = ALIVE_STATE;
+  }
+
+  cellsDst.cells[index].state = next_state;
+  cellsDst.cells[index].neighbours = neighbour_count;
+  if (next_state == DEAD_STATE || cell.state == DEAD_STATE) {
+    cellsDst.cells[index].age = 0;
   } else {
-    cellsDst.cells[index].state = DEAD_STATE;
+    cellsDst.cells[index].age = cell.age + 1;
   }
 }
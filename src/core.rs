@@ -1,23 +1,112 @@
-use std::{borrow::Cow, iter, mem};
+use std::{
+    borrow::Cow,
+    iter, mem,
+    path::PathBuf,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
+use anyhow::{bail, Context};
+use image::RgbaImage;
 use nanorand::{Rng, WyRand};
 use smaa::SmaaTarget;
 use wgpu::{util::DeviceExt, ComputePipeline};
-use winit::{event::WindowEvent, window::Window};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    },
+    window::Window,
+};
 
 use crate::{
-    camera::{Camera, CameraController, CameraUniform},
+    bloom::{self, Bloom},
+    camera::{Camera, CameraController, CameraMode, CameraUniform, ViewPreset},
+    cell::{Cell, Population},
+    clip::Clip,
+    color::ColorScheme,
     cube::Cube,
+    editor::{Editor, HighlightUniform},
+    gui::{Action, Controls, Gui},
+    guides::Guides,
+    history::History,
+    hud::Hud,
+    inspector::Inspector,
+    offscreen::{Offscreen, Resolution},
+    path::CameraPath,
+    recorder::Recorder,
+    rule::{Boundary, Rule, RulesetUniform},
     scene::{Scene, Vertex},
+    settings::{AntiAliasing, RenderMode, Settings},
+    shadow::Shadow,
     texture::Texture,
+    transparency::Transparency,
+    volume::Volume,
+};
+
+/// Slowest and fastest simulation speeds in generations per second
+const MIN_GPS: f32 = 0.125;
+const MAX_GPS: f32 = 1024.;
+
+/// Caps how many generations a slow frame can catch up on
+const MAX_STEPS_PER_FRAME: u32 = 64;
+
+/// Must match the workgroup size in compute.wgsl
+const WORKGROUP_SIZE: u32 = 256;
+/// Most workgroups wgpu allows in one dimension of a dispatch
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// Number of past generations that can be stepped back through
+const HISTORY_LENGTH: usize = 256;
+
+/// Generations the volume and points modes run between reading the cells
+/// back, so the history keeps going without a readback every step
+const READBACK_INTERVAL: usize = 16;
+
+/// How much of each new frame goes into the HUD's running averages
+const HUD_SMOOTHING: f32 = 0.05;
+
+const BACKGROUND: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
 };
 
 pub struct State {
     cell_bind_groups: Vec<wgpu::BindGroup>,
     cell_buffers: Vec<wgpu::Buffer>,
+    cell_render_bind_group_layout: wgpu::BindGroupLayout,
+    cell_render_bind_groups: Vec<wgpu::BindGroup>,
+    color_buffer: wgpu::Buffer,
+    color_scheme: ColorScheme,
+    compute_bind_group_layout: wgpu::BindGroupLayout,
     compute_pipeline: ComputePipeline,
-    frame_num: usize,
-    surface: wgpu::Surface,
+    ruleset_buffer: wgpu::Buffer,
+    rule: Rule,
+    boundary: Boundary,
+    // Used for the random first generation
+    density: f32,
+    seed: u64,
+    generation: usize,
+    gps: f32,
+    history: History,
+    last_update: Instant,
+    // Averaged over recent frames for the HUD
+    frame_time: f32,
+    steps_per_second: f32,
+    population: Population,
+    paused: bool,
+    // Fraction of the way to the next generation, cells animate in and out over it
+    step_progress: f32,
+    transition_buffer: wgpu::Buffer,
+    highlight_buffer: wgpu::Buffer,
+    clip: Clip,
+    editor: Editor,
+    cursor: Option<PhysicalPosition<f64>>,
+    // Copy of the current generation read back with the mesh, for picking
+    cells: Vec<Cell>,
+    // None when rendering headless
+    surface: Option<wgpu::Surface>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
@@ -26,58 +115,248 @@ pub struct State {
     camera_bind_group: wgpu::BindGroup,
     camera_buffer: wgpu::Buffer,
     camera_controller: CameraController,
+    camera_path: CameraPath,
+    is_playing_path: bool,
+    recorder: Recorder,
+    screenshot_scale: u32,
+    screenshot_requested: bool,
+    // Corners of the box around the live cells, None when everything is dead
+    live_bounds: Option<([i32; 3], [i32; 3])>,
     camera_uniform: CameraUniform,
     depth_texture: Texture,
     render_pipeline: wgpu::RenderPipeline,
+    render_mode: RenderMode,
+    // Only built for their render modes
+    volume: Option<Volume>,
+    points_pipeline: Option<wgpu::RenderPipeline>,
+    transparency: Option<Transparency>,
+    guides: Guides,
+    shadow: Option<Shadow>,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
-    pub smaa_target: SmaaTarget,
+    index_count: u32,
+    /// Cubes the vertex and index buffers have room for
+    mesh_capacity: usize,
+    mesh_outdated: bool,
+    /// Generation the cells were last read back from the GPU at
+    last_readback: usize,
+    // Only needed when there's a window to present to
+    smaa_target: Option<SmaaTarget>,
+    bloom: Bloom,
+    anti_aliasing: AntiAliasing,
+    offscreen: Option<Offscreen>,
+    gui: Option<Gui>,
+    hud: Option<Hud>,
+    inspector: Option<Inspector>,
     scene: Scene,
     grid_width: u32,
     total_cells: u32,
 }
 
 impl State {
-    pub async fn new(window: &Window, scene: Option<Scene>, grid_width: u32) -> Self {
-        let (_instance, surface, adapter, device, queue) = State::create_iadq(window).await;
-        let size = window.inner_size();
+    /// Without a window the state can only render offscreen, through `render_to_image`.
+    pub async fn new(
+        window: Option<&Window>,
+        size: winit::dpi::PhysicalSize<u32>,
+        scene: Option<Scene>,
+        settings: Settings,
+    ) -> anyhow::Result<Self> {
+        let Settings {
+            grid_width,
+            color_scheme,
+            gps,
+            camera_path,
+            turntable,
+            orthographic,
+            recording,
+            record_range,
+            screenshot_scale,
+            rule,
+            boundary,
+            density,
+            seed,
+            render_mode,
+            transfer,
+            floor_grid,
+            guides,
+            light_direction,
+            bloom,
+            anti_aliasing,
+        } = settings;
+        let (_instance, surface, adapter, device, queue) = State::create_iadq(window).await?;
+        let sample_count = anti_aliasing.sample_count();
         let config = State::configure_surface(&surface, &adapter, size);
-        surface.configure(&device, &config);
+        if let Some(surface) = &surface {
+            surface.configure(&device, &config);
+        }
         let shader = State::get_shader(&device);
-        let camera = Camera::new(&config, grid_width);
-        let camera_controller = CameraController::new(1.);
-        let depth_texture = Texture::create_depth_texture(&device, &config, "depth_texture");
+        let camera = Camera::new(&config, grid_width, orthographic);
+        let camera_controller = CameraController::new(1., &camera, turntable);
+        let camera_path = CameraPath::load(camera_path);
+        let depth_texture = Texture::create_depth_texture(
+            &device,
+            size.width,
+            size.height,
+            sample_count,
+            "depth_texture",
+        );
+        State::check_grid_width(&device, grid_width)?;
         let total_cells = grid_width * grid_width * grid_width;
 
-        let smaa_target = SmaaTarget::new(
+        let smaa_target = surface.as_ref().map(|_| {
+            SmaaTarget::new(
+                &device,
+                &queue,
+                size.width,
+                size.height,
+                config.format,
+                anti_aliasing.smaa_mode(),
+            )
+        });
+        let bloom = Bloom::new(
             &device,
-            &queue,
-            window.inner_size().width,
-            window.inner_size().height,
             config.format,
-            smaa::SmaaMode::Smaa1X,
+            size.width,
+            size.height,
+            bloom,
+            sample_count,
         );
 
+        let (compute_bind_group_layout, compute_pipeline) = State::setup_compute_pipeline(&device);
+
+        let ruleset_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("conway ruleset"),
+            contents: bytemuck::cast_slice(&[rule.to_uniform(grid_width, boundary)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Setting up initial cell data data
+        let initial_cell_state = match &scene {
+            Some(scene) => {
+                let mut cells = Cell::new_grid(grid_width);
+                for cube in scene.cubes.iter() {
+                    let index =
+                        Cell::index(cube.x as i32, cube.y as i32, cube.z as i32, grid_width)
+                            .unwrap();
+                    cells[index].state = Cell::ALIVE;
+                }
+                cells
+            }
+            None => Cell::random_grid(grid_width, density, seed),
+        };
+
+        let (cell_bind_groups, cell_buffers) = State::setup_cell_buffers(
+            &device,
+            &compute_bind_group_layout,
+            &ruleset_buffer,
+            &initial_cell_state,
+        );
+
+        let editor = Editor::new();
+        let (cell_render_bind_group_layout, color_buffer, transition_buffer, highlight_buffer) =
+            State::setup_cell_render_bind_group_layout(&device, &color_scheme, grid_width, &editor);
+        let cell_render_bind_groups = State::setup_cell_render_bind_groups(
+            &device,
+            &cell_render_bind_group_layout,
+            &cell_buffers,
+            &color_buffer,
+            &transition_buffer,
+            &highlight_buffer,
+        );
+
+        let shadow = Shadow::new(
+            &device,
+            &shader,
+            &cell_render_bind_group_layout,
+            light_direction,
+        );
+        shadow.write_uniform(&queue, grid_width);
         let (
             camera_bind_group,
             camera_buffer,
             camera_uniform,
-            _render_pipeline_layout,
+            render_pipeline_layout,
             render_pipeline,
-            vertex_buffer,
-            index_buffer,
-        ) = State::setup_render_pipeline(&device, &shader, &config, &camera, total_cells);
+        ) = State::setup_render_pipeline(
+            &device,
+            &shader,
+            &camera,
+            &cell_render_bind_group_layout,
+            &shadow.bind_group_layout,
+            sample_count,
+        );
+        // Only the cube mesh is lit
+        let shadow = (render_mode == RenderMode::Mesh).then_some(shadow);
+        let points_pipeline = (render_mode == RenderMode::Points)
+            .then(|| State::setup_points_pipeline(&device, &render_pipeline_layout, sample_count));
+        let transparency = (render_mode == RenderMode::Transparent).then(|| {
+            Transparency::new(
+                &device,
+                &shader,
+                &render_pipeline_layout,
+                size.width,
+                size.height,
+                sample_count,
+            )
+        });
+        let (vertex_buffer, index_buffer) = State::setup_mesh_buffers(&device, 1);
+        let volume = (render_mode == RenderMode::Volume).then(|| {
+            Volume::new(
+                &device,
+                &cell_buffers,
+                &color_buffer,
+                transfer,
+                sample_count,
+            )
+        });
+
+        let guides = Guides::new(
+            &device,
+            grid_width,
+            boundary,
+            floor_grid,
+            guides,
+            sample_count,
+        );
+        guides.write_uniform(&queue, &camera);
+
+        let gui = window.map(|window| Gui::new(&device, config.format, window, grid_width));
 
-        let (cell_bind_groups, cell_buffers, compute_pipeline) =
-            State::setup_compute_pipeline(&device, &scene, grid_width, total_cells);
+        let hud = window.map(|_| Hud::new(&device, config.format));
+        let inspector =
+            window.map(|_| Inspector::new(&device, config.format, &cell_buffers, grid_width));
 
-        let scene = scene.unwrap_or_else(|| Scene::new(total_cells));
+        let scene = scene.unwrap_or_else(|| Scene::new(0));
 
-        Self {
+        Ok(Self {
             cell_bind_groups,
             cell_buffers,
+            cell_render_bind_group_layout,
+            cell_render_bind_groups,
+            color_buffer,
+            color_scheme,
+            compute_bind_group_layout,
             compute_pipeline,
-            frame_num: 0,
+            ruleset_buffer,
+            rule,
+            boundary,
+            density,
+            seed,
+            generation: 0,
+            gps: gps.clamp(MIN_GPS, MAX_GPS),
+            history: History::new(HISTORY_LENGTH),
+            last_update: Instant::now(),
+            frame_time: 0.,
+            steps_per_second: 0.,
+            population: Population::default(),
+            paused: false,
+            step_progress: 0.,
+            transition_buffer,
+            highlight_buffer,
+            clip: Clip::new(grid_width),
+            editor,
+            cursor: None,
+            cells: initial_cell_state,
             surface,
             device,
             queue,
@@ -87,16 +366,38 @@ impl State {
             camera_bind_group,
             camera_buffer,
             camera_controller,
+            camera_path,
+            is_playing_path: false,
+            recorder: Recorder::new(recording, record_range),
+            screenshot_scale,
+            screenshot_requested: false,
+            live_bounds: None,
             camera_uniform,
             depth_texture,
             render_pipeline,
+            render_mode,
+            volume,
+            points_pipeline,
+            transparency,
+            guides,
+            shadow,
             vertex_buffer,
             index_buffer,
+            index_count: 0,
+            mesh_capacity: 1,
+            mesh_outdated: true,
+            last_readback: 0,
             smaa_target,
+            bloom,
+            anti_aliasing,
+            offscreen: None,
+            gui,
+            hud,
+            inspector,
             scene,
             grid_width,
             total_cells,
-        }
+        })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -104,189 +405,1134 @@ impl State {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture =
-                Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
-            self.smaa_target
-                .resize(&self.device, new_size.width, new_size.height);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            self.camera.resize(new_size.width, new_size.height);
+            self.depth_texture = Texture::create_depth_texture(
+                &self.device,
+                new_size.width,
+                new_size.height,
+                self.anti_aliasing.sample_count(),
+                "depth_texture",
+            );
+            if let Some(smaa_target) = &mut self.smaa_target {
+                smaa_target.resize(&self.device, new_size.width, new_size.height);
+            }
+            self.bloom
+                .prepare(&self.device, new_size.width, new_size.height);
         }
     }
 
-    #[allow(unused_variables)]
     pub fn input(&mut self, event: &WindowEvent) -> bool {
-        self.camera_controller.process_events(event)
+        if self.gui.as_mut().is_some_and(|gui| gui.input(event)) {
+            return true;
+        }
+        if let WindowEvent::CursorMoved { position, .. } = event {
+            self.cursor = Some(*position);
+        }
+
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::C => {
+                    self.color_scheme.mode = self.color_scheme.mode.next();
+                    self.write_color_uniform();
+                    true
+                }
+                VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => {
+                    self.set_gps(self.gps * 2.);
+                    true
+                }
+                VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                    self.set_gps(self.gps / 2.);
+                    true
+                }
+                VirtualKeyCode::Space => {
+                    self.paused = !self.paused;
+                    // Cells can only be edited while paused
+                    if !self.paused && self.editor.enabled {
+                        self.editor.toggle();
+                    }
+                    true
+                }
+                VirtualKeyCode::Period => {
+                    self.paused = true;
+                    self.step(1);
+                    // Still play the transition while paused
+                    self.step_progress = 0.;
+                    true
+                }
+                VirtualKeyCode::Comma => {
+                    self.paused = true;
+                    self.step_back();
+                    true
+                }
+                VirtualKeyCode::F => {
+                    self.camera_controller.toggle_mode(&self.camera);
+                    true
+                }
+                VirtualKeyCode::O => {
+                    self.camera.toggle_orthographic();
+                    true
+                }
+                VirtualKeyCode::T => {
+                    self.camera_controller.toggle_turntable(&self.camera);
+                    true
+                }
+                VirtualKeyCode::Key1 => {
+                    self.camera_controller
+                        .view_preset(&self.camera, ViewPreset::Front);
+                    true
+                }
+                VirtualKeyCode::Key2 => {
+                    self.camera_controller
+                        .view_preset(&self.camera, ViewPreset::Top);
+                    true
+                }
+                VirtualKeyCode::Key3 => {
+                    self.camera_controller
+                        .view_preset(&self.camera, ViewPreset::Side);
+                    true
+                }
+                VirtualKeyCode::Key4 => {
+                    self.camera_controller
+                        .view_preset(&self.camera, ViewPreset::Isometric);
+                    true
+                }
+                VirtualKeyCode::Key0 | VirtualKeyCode::Home => {
+                    self.refresh_cells();
+                    if let Some((min, max)) = self.live_bounds {
+                        let min = min.map(|n| n as f32).into();
+                        let max = max.map(|n| n as f32 + 1.).into();
+                        self.camera_controller.frame(&self.camera, min, max);
+                    }
+                    true
+                }
+                VirtualKeyCode::K => {
+                    self.camera_path.record(self.generation, self.camera.view());
+                    true
+                }
+                VirtualKeyCode::P => {
+                    self.is_playing_path = !self.is_playing_path && !self.camera_path.is_empty();
+                    if !self.is_playing_path {
+                        // Carry on from wherever playback left the camera
+                        self.camera_controller.reset_to(&self.camera);
+                    }
+                    true
+                }
+                VirtualKeyCode::G => {
+                    if let Some(gui) = &mut self.gui {
+                        gui.toggle();
+                    }
+                    true
+                }
+                VirtualKeyCode::H => {
+                    if let Some(hud) = &mut self.hud {
+                        hud.toggle();
+                    }
+                    true
+                }
+                VirtualKeyCode::V => {
+                    self.guides.toggle();
+                    true
+                }
+                VirtualKeyCode::X => {
+                    let mut clip = self.clip;
+                    clip.axis = clip.axis.next();
+                    self.set_clip(clip);
+                    true
+                }
+                VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                    let mut clip = self.clip;
+                    clip.move_layer(if *keycode == VirtualKeyCode::PageUp {
+                        1
+                    } else {
+                        -1
+                    });
+                    self.set_clip(clip);
+                    true
+                }
+                VirtualKeyCode::L => {
+                    let mut clip = self.clip;
+                    clip.single_layer = !clip.single_layer;
+                    self.set_clip(clip);
+                    true
+                }
+                VirtualKeyCode::I => {
+                    if let Some(inspector) = &mut self.inspector {
+                        inspector.toggle();
+                    }
+                    true
+                }
+                VirtualKeyCode::M => {
+                    self.refresh_cells();
+                    self.editor.toggle();
+                    self.paused |= self.editor.enabled;
+                    true
+                }
+                VirtualKeyCode::Tab => {
+                    self.editor.tool = self.editor.tool.next();
+                    true
+                }
+                VirtualKeyCode::B => {
+                    self.editor.shape = self.editor.shape.next();
+                    true
+                }
+                VirtualKeyCode::LBracket => {
+                    self.editor.resize_brush(-1);
+                    true
+                }
+                VirtualKeyCode::RBracket => {
+                    self.editor.resize_brush(1);
+                    true
+                }
+                VirtualKeyCode::F12 => {
+                    self.screenshot_requested = true;
+                    true
+                }
+                VirtualKeyCode::R => {
+                    self.recorder.toggle(self.generation, 1. / self.gps);
+                    true
+                }
+                _ => self.camera_controller.process_events(event),
+            },
+            WindowEvent::CursorMoved { position, .. } if self.editor.enabled => {
+                self.editor.set_cursor(*position);
+                self.pick();
+                let edits = self.editor.drag(self.grid_width, &self.cells);
+                self.apply_edits(&edits);
+                // Still pans with the right button
+                self.camera_controller.process_events(event)
+            }
+            // The left button edits instead of orbiting
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } if self.editor.enabled => {
+                match state {
+                    ElementState::Pressed => {
+                        // Clicking a pixel in the inspector toggles that cell
+                        let edits = match self.inspected_cell() {
+                            Some([x, y, z]) => {
+                                let i = Cell::index(x, y, z, self.grid_width).unwrap();
+                                let state = if self.cells[i].is_solid() {
+                                    Cell::DEAD
+                                } else {
+                                    Cell::ALIVE
+                                };
+                                vec![(i, state)]
+                            }
+                            None => self.editor.press(self.grid_width, &self.cells),
+                        };
+                        self.apply_edits(&edits);
+                    }
+                    ElementState::Released => self.editor.release(),
+                }
+                true
+            }
+            // Scrolling over the inspector moves through the layers instead of zooming
+            WindowEvent::MouseWheel { delta, .. } if self.inspected_cell().is_some() => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.,
+                };
+                if let Some(inspector) = &mut self.inspector {
+                    inspector.scroll(lines);
+                }
+                true
+            }
+            _ => self.camera_controller.process_events(event),
+        }
+    }
+
+    /// The cell under the cursor in the inspector, if it's showing.
+    fn inspected_cell(&mut self) -> Option<[i32; 3]> {
+        self.inspector
+            .as_mut()
+            .and_then(|inspector| inspector.hover(self.cursor, self.size))
+    }
+
+    /// Hides the cells past the clip planes, looking straight at the layer
+    /// when only one is shown.
+    fn set_clip(&mut self, clip: Clip) {
+        let faces_layer =
+            clip.single_layer && (!self.clip.single_layer || clip.axis != self.clip.axis);
+        if faces_layer {
+            self.camera_controller
+                .view_preset(&self.camera, clip.axis.view_preset());
+        }
+        self.clip = clip;
+        self.mesh_outdated = true;
+    }
+
+    /// Updates which cell is under the cursor and highlights it, whether
+    /// it's in the 3D view or the inspector.
+    fn pick(&mut self) {
+        self.editor.pick(
+            self.camera.build_view_projection_matrix(),
+            self.size,
+            self.grid_width,
+            &self.cells,
+            &self.clip,
+        );
+        let highlight = match self.inspected_cell() {
+            Some(cell) => HighlightUniform::cell(cell),
+            None => self.editor.to_uniform(),
+        };
+        self.queue.write_buffer(
+            &self.highlight_buffer,
+            0,
+            bytemuck::cast_slice(&[highlight]),
+        );
+    }
+
+    /// Writes edited cells straight into the buffer holding the current generation.
+    fn apply_edits(&mut self, edits: &[(usize, i32)]) {
+        if edits.is_empty() {
+            return;
+        }
+        let cell_buffer = &self.cell_buffers[self.generation % 2];
+        for &(index, state) in edits {
+            let cell = &mut self.cells[index];
+            cell.state = state;
+            cell.age = 0;
+            self.queue.write_buffer(
+                cell_buffer,
+                (index * mem::size_of::<Cell>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&[*cell]),
+            );
+        }
+        // No growing or shrinking, edits show up straight away
+        self.finish_transition();
+        self.mesh_outdated = true;
+    }
+
+    pub fn mouse_motion(&mut self, delta: (f64, f64)) {
+        self.camera_controller.process_mouse_motion(delta);
+    }
+
+    /// Whether the cursor should be grabbed and hidden, for mouse look.
+    pub fn grabs_cursor(&self) -> bool {
+        self.camera_controller.mode() == CameraMode::Fly
+    }
+
+    fn set_gps(&mut self, gps: f32) {
+        self.gps = gps.clamp(MIN_GPS, MAX_GPS);
+        println!("{} generations per second", self.gps);
+    }
+
+    fn write_color_uniform(&mut self) {
+        let color_uniform = self.color_scheme.to_uniform(self.grid_width);
+        self.queue.write_buffer(
+            &self.color_buffer,
+            0,
+            bytemuck::cast_slice(&[color_uniform]),
+        );
+    }
+
+    fn write_ruleset_uniform(&mut self) {
+        let ruleset = self.rule.to_uniform(self.grid_width, self.boundary);
+        self.queue
+            .write_buffer(&self.ruleset_buffer, 0, bytemuck::cast_slice(&[ruleset]));
+    }
+
+    /// Lays out the settings panel and applies whatever was changed in it.
+    pub fn run_gui(&mut self, window: &Window) {
+        let before = Controls {
+            rule: self.rule,
+            boundary: self.boundary,
+            gps: self.gps,
+            min_gps: MIN_GPS,
+            max_gps: MAX_GPS,
+            color_mode: self.color_scheme.mode,
+            density: self.density,
+            seed: self.seed,
+            clip: self.clip,
+        };
+        let mut controls = before;
+        let action = match &mut self.gui {
+            Some(gui) => gui.run(window, &mut controls),
+            None => return,
+        };
+
+        if controls.rule != before.rule || controls.boundary != before.boundary {
+            self.rule = controls.rule;
+            self.boundary = controls.boundary;
+            self.write_ruleset_uniform();
+            self.guides
+                .set_grid(&self.device, self.grid_width, self.boundary);
+        }
+        if controls.gps != before.gps {
+            self.gps = controls.gps.clamp(MIN_GPS, MAX_GPS);
+        }
+        if controls.color_mode != before.color_mode {
+            self.color_scheme.mode = controls.color_mode;
+            self.write_color_uniform();
+        }
+        if controls.clip != before.clip {
+            self.set_clip(controls.clip);
+        }
+        self.density = controls.density;
+        self.seed = controls.seed;
+
+        match action {
+            Some(Action::Reset { grid_width }) => self.reset(grid_width),
+            Some(Action::Randomise { grid_width }) => {
+                self.seed = WyRand::new().generate();
+                self.reset(grid_width);
+            }
+            None => {}
+        }
+    }
+
+    /// Starts over from a random first generation, rebuilding the
+    /// grid sized buffers if the width changed.
+    fn reset(&mut self, grid_width: u32) {
+        if let Err(e) = State::check_grid_width(&self.device, grid_width) {
+            eprintln!("{:?}", e);
+            return;
+        }
+        if grid_width != self.grid_width {
+            self.grid_width = grid_width;
+            self.total_cells = grid_width * grid_width * grid_width;
+            let cells = Cell::new_grid(grid_width);
+            let (cell_bind_groups, cell_buffers) = State::setup_cell_buffers(
+                &self.device,
+                &self.compute_bind_group_layout,
+                &self.ruleset_buffer,
+                &cells,
+            );
+            self.cell_render_bind_groups = State::setup_cell_render_bind_groups(
+                &self.device,
+                &self.cell_render_bind_group_layout,
+                &cell_buffers,
+                &self.color_buffer,
+                &self.transition_buffer,
+                &self.highlight_buffer,
+            );
+            self.cell_bind_groups = cell_bind_groups;
+            self.cell_buffers = cell_buffers;
+            if let Some(volume) = &mut self.volume {
+                volume.set_cell_buffers(&self.device, &self.cell_buffers, &self.color_buffer);
+            }
+            self.write_ruleset_uniform();
+            self.write_color_uniform();
+
+            self.camera.set_grid_width(grid_width);
+            self.guides
+                .set_grid(&self.device, grid_width, self.boundary);
+            if let Some(shadow) = &self.shadow {
+                shadow.write_uniform(&self.queue, grid_width);
+            }
+            self.clip = Clip::new(grid_width);
+            if let Some(inspector) = &mut self.inspector {
+                inspector.set_cell_buffers(&self.device, &self.cell_buffers, grid_width);
+            }
+            let width = grid_width as f32;
+            self.camera_controller.frame(
+                &self.camera,
+                (0., 0., 0.).into(),
+                (width, width, width).into(),
+            );
+        }
+
+        let cells = Cell::random_grid(self.grid_width, self.density, self.seed);
+        for cell_buffer in self.cell_buffers.iter() {
+            self.queue
+                .write_buffer(cell_buffer, 0, bytemuck::cast_slice(&cells));
+        }
+        self.cells = cells;
+        self.generation = 0;
+        self.last_readback = 0;
+        self.step_progress = 0.;
+        self.history = History::new(HISTORY_LENGTH);
+        self.live_bounds = None;
+        self.mesh_outdated = true;
+    }
+
+    /// Each cell buffer is bound whole, so it has to fit in one storage binding.
+    fn check_grid_width(device: &wgpu::Device, grid_width: u32) -> anyhow::Result<()> {
+        let max_size = device.limits().max_storage_buffer_binding_size as u64;
+        let max_width = Cell::max_grid_width(max_size);
+        if grid_width > max_width {
+            let cells_size = (grid_width as u64).pow(3) * mem::size_of::<Cell>() as u64;
+            bail!(
+                "A grid {} wide needs {} MB per cell buffer, but this GPU allows at most {} MB, enough for {} wide",
+                grid_width,
+                cells_size >> 20,
+                max_size >> 20,
+                max_width
+            );
+        }
+        Ok(())
     }
 
     pub fn update(&mut self) {
-        self.camera_controller.update_camera(&mut self.camera);
+        let now = Instant::now();
+        let dt = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        self.update_camera(dt);
+        // The camera may have moved under the cursor
+        self.pick();
+
+        self.step_progress += dt * self.gps;
+
+        let mut steps = 0;
+        if self.paused {
+            // Let the last transition finish but don't start another
+            self.step_progress = self.step_progress.min(1.);
+        } else {
+            // Slow rates step every few frames, fast rates batch several steps
+            // into one frame and drop whatever is beyond the cap
+            steps = (self.step_progress as u32).min(MAX_STEPS_PER_FRAME);
+            // Every recorded generation gets a frame, however fast the rate
+            if let Some(max_steps) = self.recorder.max_steps(self.generation) {
+                steps = steps.min(max_steps);
+            }
+            if steps > 0 {
+                self.step(steps);
+                self.step_progress = self.step_progress.fract();
+            }
+        }
+
+        if dt > 0. {
+            self.frame_time += (dt - self.frame_time) * HUD_SMOOTHING;
+            self.steps_per_second += (steps as f32 / dt - self.steps_per_second) * HUD_SMOOTHING;
+        }
+
+        self.write_transition();
+    }
+
+    /// Moves the camera on by `dt` seconds, along the camera path while it's playing.
+    pub fn update_camera(&mut self, dt: f32) {
+        let path_view = self
+            .camera_path
+            .sample(self.generation as f32 + self.step_progress.min(1.));
+        match path_view {
+            Some(view) if self.is_playing_path => self.camera.set_view(&view),
+            _ => self.camera_controller.update_camera(&mut self.camera, dt),
+        }
+        self.write_camera_uniform();
+    }
+
+    fn write_camera_uniform(&mut self) {
         self.camera_uniform.update_view_proj(&self.camera);
         self.queue.write_buffer(
             &self.camera_buffer,
             0,
             bytemuck::cast_slice(&[self.camera_uniform]),
         );
+        if let Some(volume) = &self.volume {
+            volume.write_uniform(&self.queue, &self.camera, self.grid_width);
+        }
+        self.guides.write_uniform(&self.queue, &self.camera);
+    }
+
+    /// Starts the birth/death transition into the generation just stepped to.
+    pub fn start_transition(&mut self) {
+        self.step_progress = 0.;
+        self.write_transition();
+    }
+
+    /// Ends the current birth/death transition, so cells are drawn at full size.
+    pub fn finish_transition(&mut self) {
+        self.step_progress = 1.;
+        self.write_transition();
+    }
+
+    fn write_transition(&mut self) {
+        self.queue.write_buffer(
+            &self.transition_buffer,
+            0,
+            bytemuck::cast_slice(&[self.step_progress]),
+        );
+    }
+
+    /// Plays the camera path, if there is one, rather than using the controller.
+    pub fn play_camera_path(&mut self) {
+        self.is_playing_path = !self.camera_path.is_empty();
     }
 
     pub async fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+        if self.mesh_outdated && self.needs_readback() {
+            self.rebuild_mesh().await;
+        }
+
+        let output = match &self.surface {
+            Some(surface) => surface.get_current_texture()?,
+            None => return Ok(()),
+        };
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-        let smaa_frame = self
-            .smaa_target
-            .start_frame(&self.device, &self.queue, &view);
+        // Taken out while drawing, the SMAA frame borrows it for the whole frame
+        let mut smaa_target = self.smaa_target.take().unwrap();
+        let smaa_frame = smaa_target.start_frame(&self.device, &self.queue, &view);
 
+        if let Some(transparency) = &mut self.transparency {
+            transparency.prepare(&self.device, self.size.width, self.size.height);
+        }
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        self.draw_scene(
+            &mut encoder,
+            &smaa_frame,
+            &self.depth_texture.view,
+            self.size,
+            BACKGROUND,
+        );
+        self.queue.submit(iter::once(encoder.finish()));
 
-        // Recalculate Vertices
-        let computed_cell_buffer = &self.cell_buffers[self.frame_num % 2];
-        let cell_buffer_slice = computed_cell_buffer.slice(..);
-        let cell_buffer_future = cell_buffer_slice.map_async(wgpu::MapMode::Read);
-        self.device.poll(wgpu::Maintain::Wait);
+        smaa_frame.resolve();
+        self.smaa_target = Some(smaa_target);
 
-        let mut scene = Scene::new(self.total_cells);
-        if let Ok(()) = cell_buffer_future.await {
-            // Gets contents of buffer
-            let data = cell_buffer_slice.get_mapped_range();
-            // Since contents are got in bytes, this converts these bytes back to i32
-            let result: Vec<i32> = bytemuck::cast_slice(&data).to_vec();
-
-            for result_chunk in result.chunks(4) {
-                let state = result_chunk[0] as f32;
-                let x = result_chunk[1] as f32;
-                let y = result_chunk[2] as f32;
-                let z = result_chunk[3] as f32;
-
-                if state == 1f32 {
-                    scene.add_cube(Cube::new(
-                        x,
-                        y,
-                        z,
-                        1.,
-                        [
-                            x / self.grid_width as f32,
-                            y / self.grid_width as f32,
-                            z / self.grid_width as f32,
-                        ],
-                    ))
-                }
+        // Drawn after SMAA so text stays sharp
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Overlay Encoder"),
+            });
+        if let Some(inspector) = self
+            .inspector
+            .as_ref()
+            .filter(|inspector| inspector.visible)
+        {
+            inspector.draw(&self.queue, &mut encoder, &view, self.size, self.generation);
+        }
+        let hud_lines = self.hud_lines();
+        if let Some(hud) = self.hud.as_mut().filter(|hud| hud.visible) {
+            hud.set_text(&self.device, &self.queue, &hud_lines, self.size);
+            hud.draw(&mut encoder, &view);
+        }
+        if let Some(gui) = &mut self.gui {
+            gui.draw(&self.device, &self.queue, &mut encoder, &view, self.size);
+        }
+        self.queue.submit(iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+
+    /// Text for the HUD, one entry per line.
+    fn hud_lines(&self) -> Vec<String> {
+        let population = self.population;
+        let rule = self.rule.to_string();
+        let mut lines = vec![
+            format!("Generation {}", self.generation),
+            format!("Live {}", population.live),
+            format!("Births {}  Deaths {}", population.births, population.deaths),
+            if self.paused {
+                "Paused".to_string()
+            } else {
+                format!("Rate {:.1} gen/s of {}", self.steps_per_second, self.gps)
+            },
+            format!("Frame {:.1} ms", self.frame_time * 1000.),
+            format!("Rule {} {:?}", rule, self.boundary),
+        ];
+        if let Some(inspector) = self
+            .inspector
+            .as_ref()
+            .filter(|inspector| inspector.visible)
+        {
+            lines.push(format!("Inspecting Z layer {}", inspector.layer));
+        }
+        if self.clip.is_active() {
+            lines.push(self.clip.to_string());
+        }
+        if self.editor.enabled {
+            lines.push(format!("Edit {}", self.editor));
+        }
+        lines
+    }
+
+    /// Takes any screenshot that was asked for and records the current
+    /// generation, after it's been drawn to the window.
+    pub async fn capture(&mut self) {
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            self.screenshot().await;
+        }
+        self.record().await;
+    }
+
+    /// Saves the frame as a timestamped PNG, at a multiple of the window size.
+    async fn screenshot(&mut self) {
+        // Stay within the largest texture the device allows
+        let largest = self.size.width.max(self.size.height);
+        let max_scale = self.device.limits().max_texture_dimension_2d / largest;
+        let scale = self.screenshot_scale.min(max_scale).max(1);
+        if scale < self.screenshot_scale {
+            println!("Screenshot scale reduced to {} to fit the GPU", scale);
+        }
+        let resolution = Resolution {
+            width: self.size.width * scale,
+            height: self.size.height * scale,
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+        let file = PathBuf::from(format!("screenshot-{}.png", timestamp));
+        let saved = self
+            .render_to_image(resolution, false)
+            .await
+            .and_then(|image| {
+                image
+                    .save(&file)
+                    .with_context(|| format!("Couldn't save {}", file.display()))
+            });
+        match saved {
+            Ok(()) => println!("Saved screenshot {}", file.display()),
+            Err(e) => eprintln!("{:?}", e),
+        }
+    }
+
+    /// Captures the current generation if it's being recorded, with the
+    /// transition finished and a transparent background.
+    async fn record(&mut self) {
+        if self.recorder.wants_frame(self.generation) {
+            let step_progress = self.step_progress;
+            self.finish_transition();
+            let (width, height) = self
+                .recorder
+                .frame_size()
+                .unwrap_or((self.size.width, self.size.height));
+            let resolution = Resolution { width, height };
+            match self.render_to_image(resolution, true).await {
+                Ok(frame) => self.recorder.add_frame(self.generation, frame),
+                Err(e) => eprintln!("{:?}", e),
             }
+            self.step_progress = step_progress;
+            self.write_transition();
+        }
 
-            drop(data);
-            computed_cell_buffer.unmap();
+        if self.recorder.is_finished(self.generation) {
+            self.recorder.finish(1. / self.gps);
         }
+    }
 
-        let (vertices, indices) = scene.get_vertices_and_indices();
-        self.queue
-            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
-        self.queue
-            .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&indices));
+    /// Saves anything recorded so far, e.g. before quitting.
+    pub fn stop_recording(&mut self) {
+        if self.recorder.is_recording() {
+            self.recorder.finish(1. / self.gps);
+        }
+    }
 
-        self.scene = scene;
+    /// Renders the current generation into an image of any size, using the
+    /// same pipeline and SMAA as the window but without presenting anything.
+    /// A transparent image has no background behind the cells.
+    pub async fn render_to_image(
+        &mut self,
+        resolution: Resolution,
+        transparent: bool,
+    ) -> anyhow::Result<RgbaImage> {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        if resolution.width > max_dimension || resolution.height > max_dimension {
+            bail!(
+                "{}x{} is bigger than the largest image this GPU can render, {}x{}",
+                resolution.width,
+                resolution.height,
+                max_dimension,
+                max_dimension
+            );
+        }
 
-        // Run Compute Pass
-        encoder.push_debug_group("compute boid movement");
-        {
-            let mut cpass =
-                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-            cpass.set_pipeline(&self.compute_pipeline);
-            cpass.set_bind_group(0, &self.cell_bind_groups[(self.frame_num + 1) % 2], &[]);
-            cpass.dispatch(65535, 1, 1);
+        if self.mesh_outdated && self.needs_readback() {
+            self.rebuild_mesh().await;
         }
-        encoder.pop_debug_group();
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &*smaa_frame,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
+        let mut offscreen = match self.offscreen.take() {
+            Some(offscreen) if offscreen.resolution == resolution => offscreen,
+            _ => Offscreen::new(
+                &self.device,
+                &self.queue,
+                self.config.format,
+                resolution,
+                self.anti_aliasing,
+            ),
+        };
+
+        // Match the image's aspect ratio for just this frame
+        self.camera.resize(resolution.width, resolution.height);
+        self.write_camera_uniform();
+        if let Some(transparency) = &mut self.transparency {
+            transparency.prepare(&self.device, resolution.width, resolution.height);
+        }
+        self.bloom
+            .prepare(&self.device, resolution.width, resolution.height);
+
+        let smaa_frame =
+            offscreen
+                .smaa_target
+                .start_frame(&self.device, &self.queue, &offscreen.view);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Render Encoder"),
             });
+        self.draw_scene(
+            &mut encoder,
+            &smaa_frame,
+            &offscreen.depth_texture.view,
+            PhysicalSize::new(resolution.width, resolution.height),
+            if transparent {
+                wgpu::Color::TRANSPARENT
+            } else {
+                BACKGROUND
+            },
+        );
+        self.queue.submit(iter::once(encoder.finish()));
+        smaa_frame.resolve();
 
-            render_pass.set_pipeline(&self.render_pipeline);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Copy Encoder"),
+            });
+        offscreen.copy_to_buffer(&mut encoder);
+        self.queue.submit(iter::once(encoder.finish()));
+        let image = offscreen.read(&self.device).await;
+
+        self.camera.resize(self.size.width, self.size.height);
+        self.write_camera_uniform();
+        self.bloom
+            .prepare(&self.device, self.size.width, self.size.height);
+        self.offscreen = Some(offscreen);
+        image
+    }
+
+    fn draw_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        depth: &wgpu::TextureView,
+        size: PhysicalSize<u32>,
+        background: wgpu::Color,
+    ) {
+        if let Some(shadow) = &self.shadow {
+            let mut render_pass = shadow.begin_pass(encoder);
+            self.draw_mesh(&mut render_pass);
+        }
+        if let Some(transparency) = &self.transparency {
+            let mut render_pass = transparency.begin_accumulate(encoder);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+            self.draw_mesh(&mut render_pass);
         }
 
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &self.bloom.scene_attachments(background),
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        // First, so the volume and transparent cubes blend over them
+        if self.guides.visible {
+            self.guides.draw(&mut render_pass);
+        }
+        self.draw_cells(&mut render_pass);
+        if self.guides.visible {
+            self.guides.draw_gizmo(&mut render_pass, size);
+        }
+        drop(render_pass);
+
+        self.bloom.draw(encoder, target);
+    }
+
+    fn draw_cells<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if let Some(volume) = &self.volume {
+            volume.draw(render_pass, self.generation);
+            return;
+        }
+
+        if let Some(transparency) = &self.transparency {
+            transparency.composite(render_pass);
+            return;
+        }
+
+        if let Some(points_pipeline) = &self.points_pipeline {
+            // An instance per cell, dead ones are dropped in the vertex shader
+            render_pass.set_pipeline(points_pipeline);
+            self.set_cell_bind_groups(render_pass);
+            render_pass.draw(0..6, 0..self.total_cells);
+            return;
+        }
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        if let Some(shadow) = &self.shadow {
+            render_pass.set_bind_group(2, &shadow.bind_group, &[]);
+        }
+        self.draw_mesh(render_pass);
+    }
+
+    fn set_cell_bind_groups<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.cell_render_bind_groups[self.generation % 2], &[]);
+    }
+
+    /// Draws the cubes with whichever pipeline and view the pass has set.
+    fn draw_mesh<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(1, &self.cell_render_bind_groups[self.generation % 2], &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+
+    /// Computes the next `count` generations, ping-ponging between the cell buffers.
+    pub fn step(&mut self, count: u32) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Encoder"),
+            });
+
+        // Big grids need more workgroups than fit in one dimension
+        let workgroups = self.total_cells.div_ceil(WORKGROUP_SIZE);
+        let rows = workgroups.div_ceil(MAX_WORKGROUPS);
+        let columns = workgroups.min(MAX_WORKGROUPS);
+
+        encoder.push_debug_group("compute cell generations");
+        for _ in 0..count {
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&self.compute_pipeline);
+                cpass.set_bind_group(0, &self.cell_bind_groups[self.generation % 2], &[]);
+                cpass.dispatch(columns, rows, 1);
+            }
+            self.generation += 1;
+        }
+        encoder.pop_debug_group();
+
         self.queue.submit(iter::once(encoder.finish()));
 
-        smaa_frame.resolve();
-        output.present();
+        self.mesh_outdated = true;
+    }
 
-        self.frame_num += 1;
+    /// Restores the most recent generation in the history before the current one.
+    fn step_back(&mut self) {
+        // Record the current generation first so it's the one dropped
+        self.refresh_cells();
+        if let Some(snapshot) = self.history.rewind() {
+            // Both buffers get the same cells so nothing animates
+            let cells = snapshot.to_cells(self.grid_width);
+            for cell_buffer in self.cell_buffers.iter() {
+                self.queue
+                    .write_buffer(cell_buffer, 0, bytemuck::cast_slice(&cells));
+            }
+            self.generation = snapshot.generation;
+            self.step_progress = 1.;
+            self.mesh_outdated = true;
+        }
+    }
 
-        Ok(())
+    /// Whether the cells need reading back from the GPU. Only the cube modes
+    /// build a mesh from them, the others just need them for rewinding,
+    /// editing and the HUD, so big grids only do it every few generations
+    /// while running.
+    fn needs_readback(&self) -> bool {
+        self.builds_mesh()
+            || self.paused
+            || self.hud.as_ref().is_some_and(|hud| hud.visible)
+            || self.generation >= self.last_readback + READBACK_INTERVAL
+    }
+
+    /// The volume and points are drawn straight from the cell buffer.
+    fn builds_mesh(&self) -> bool {
+        matches!(self.render_mode, RenderMode::Mesh | RenderMode::Transparent)
+    }
+
+    /// Reads back the cells now if they're behind the GPU, for things that
+    /// can't wait for the next frame like framing the live cells.
+    fn refresh_cells(&mut self) {
+        if self.mesh_outdated {
+            pollster::block_on(self.rebuild_mesh());
+        }
+    }
+
+    /// Rebuilds the cubes from the current generation, along with the cells
+    /// that died since the previous one so they can shrink away.
+    async fn rebuild_mesh(&mut self) {
+        let builds_mesh = self.builds_mesh();
+        let current_buffer = &self.cell_buffers[self.generation % 2];
+        let previous_buffer = &self.cell_buffers[(self.generation + 1) % 2];
+        let current_slice = current_buffer.slice(..);
+        let previous_slice = previous_buffer.slice(..);
+        let current_future = current_slice.map_async(wgpu::MapMode::Read);
+        let previous_future = previous_slice.map_async(wgpu::MapMode::Read);
+        self.device.poll(wgpu::Maintain::Wait);
+
+        // The last mesh is a good guess at the size of this one
+        let mut scene = Scene::new(if builds_mesh {
+            self.scene.cubes.len() as u32
+        } else {
+            0
+        });
+        if let (Ok(()), Ok(())) = (current_future.await, previous_future.await) {
+            // Gets contents of buffers
+            let current_data = current_slice.get_mapped_range();
+            let previous_data = previous_slice.get_mapped_range();
+            // Since contents are got in bytes, this converts these bytes back to cells
+            let cells: &[Cell] = bytemuck::cast_slice(&current_data);
+            let previous_cells: &[Cell] = bytemuck::cast_slice(&previous_data);
+
+            self.history.record(self.generation, cells);
+            self.population = Population::count(cells, previous_cells);
+            self.cells.copy_from_slice(cells);
+
+            // Clipped cells count as empty, so the cut faces get drawn
+            let clip = self.clip;
+            let is_alive = |x: i32, y: i32, z: i32| {
+                clip.contains(x, y, z)
+                    && Cell::index(x, y, z, self.grid_width).is_some_and(|i| cells[i].is_solid())
+            };
+
+            self.live_bounds =
+                cells
+                    .iter()
+                    .filter(|cell| cell.is_solid())
+                    .fold(None, |bounds, cell| {
+                        let (mut min, mut max) = bounds.unwrap_or(([i32::MAX; 3], [i32::MIN; 3]));
+                        for (k, n) in [cell.x, cell.y, cell.z].into_iter().enumerate() {
+                            min[k] = min[k].min(n);
+                            max[k] = max[k].max(n);
+                        }
+                        Some((min, max))
+                    });
+
+            // Every face is kept when cells behind can show through
+            let transparent = self.render_mode == RenderMode::Transparent;
+            let is_covered = |x: i32, y: i32, z: i32| !transparent && is_alive(x, y, z);
+            for (cell, previous) in cells.iter().zip(previous_cells) {
+                if builds_mesh
+                    && (cell.is_solid() || previous.is_solid())
+                    && clip.contains(cell.x, cell.y, cell.z)
+                {
+                    scene.add_cube(Cube::new_occluded(
+                        cell.x as f32,
+                        cell.y as f32,
+                        cell.z as f32,
+                        1.,
+                        is_covered,
+                    ))
+                }
+            }
+
+            drop(current_data);
+            drop(previous_data);
+            current_buffer.unmap();
+            previous_buffer.unmap();
+        }
+
+        let (vertices, indices) = scene.get_vertices_and_indices();
+        if scene.cubes.len() > self.mesh_capacity {
+            // Grown to the next power of two so a growing population doesn't
+            // reallocate every generation
+            self.mesh_capacity = scene.cubes.len().next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) =
+                State::setup_mesh_buffers(&self.device, self.mesh_capacity);
+        }
+        self.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.queue
+            .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&indices));
+
+        self.index_count = indices.len() as u32;
+        self.scene = scene;
+        self.last_readback = self.generation;
+        self.mesh_outdated = false;
     }
 
     async fn create_iadq(
-        window: &Window,
-    ) -> (
+        window: Option<&Window>,
+    ) -> anyhow::Result<(
         wgpu::Instance,
-        wgpu::Surface,
+        Option<wgpu::Surface>,
         wgpu::Adapter,
         wgpu::Device,
         wgpu::Queue,
-    ) {
+    )> {
         // The instance is a handle to our GPU
         // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
         let instance = wgpu::Instance::new(wgpu::Backends::all());
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let surface = window.map(|window| unsafe { instance.create_surface(window) });
+        // Headless machines often have no GPU, so prefer a software adapter there
+        let mut adapter = None;
+        if surface.is_none() {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                })
+                .await;
+        }
+        if adapter.is_none() {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    compatible_surface: surface.as_ref(),
+                    force_fallback_adapter: false,
+                })
+                .await;
+        }
+        let adapter = adapter.context("No graphics adapter found")?;
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
+                    // Cell buffers for big grids go well past the default 128MB
+                    limits: wgpu::Limits {
+                        max_storage_buffer_binding_size: adapter
+                            .limits()
+                            .max_storage_buffer_binding_size,
+                        ..wgpu::Limits::default()
+                    },
                     label: None,
                 },
                 None, // Trace path
             )
             .await
-            .unwrap();
+            .context("Couldn't open the graphics device")?;
 
-        (instance, surface, adapter, device, queue)
+        Ok((instance, surface, adapter, device, queue))
     }
 
     fn configure_surface(
-        surface: &wgpu::Surface,
+        surface: &Option<wgpu::Surface>,
         adapter: &wgpu::Adapter,
         size: winit::dpi::PhysicalSize<u32>,
     ) -> wgpu::SurfaceConfiguration {
+        // Offscreen frames are read back as RGBA anyway
+        let format = match surface {
+            Some(surface) => surface.get_preferred_format(adapter).unwrap(),
+            None => wgpu::TextureFormat::Rgba8UnormSrgb,
+        };
         wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_preferred_format(adapter).unwrap(),
+            format,
             width: size.width,
             height: size.height,
             present_mode: wgpu::PresentMode::Fifo,
@@ -296,43 +1542,20 @@ impl State {
     fn get_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
         device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("color.wgsl"), include_str!("shader.wgsl")).into(),
+            ),
         })
     }
 
-    fn setup_compute_pipeline(
-        device: &wgpu::Device,
-        scene: &Option<Scene>,
-        grid_width: u32,
-        total_cells: u32,
-    ) -> (Vec<wgpu::BindGroup>, Vec<wgpu::Buffer>, ComputePipeline) {
+    fn setup_compute_pipeline(device: &wgpu::Device) -> (wgpu::BindGroupLayout, ComputePipeline) {
         // Compute
         let compute_shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: None,
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("compute.wgsl"))),
         });
 
-        // Following Death/Survive/Birth -> 0/1/2
-        let birth_list: Vec<u32> = vec![4];
-        let survive_list: Vec<u32> = vec![5, 6];
-
-        let mut ruleset_list: Vec<u32> = vec![0; 28];
-        for birth in birth_list {
-            ruleset_list[birth as usize] = 2;
-        }
-
-        for survive in survive_list {
-            ruleset_list[survive as usize] = 1;
-        }
-
-        ruleset_list[27] = grid_width;
-
-        let rulset_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("conway ruleset"),
-            contents: bytemuck::cast_slice(&ruleset_list),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
+        // Cell buffers are left unsized so the grid can be resized without a new pipeline
         let compute_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -343,7 +1566,7 @@ impl State {
                             ty: wgpu::BufferBindingType::Uniform,
                             has_dynamic_offset: false,
                             min_binding_size: wgpu::BufferSize::new(
-                                (ruleset_list.len() * mem::size_of::<u32>()) as _,
+                                mem::size_of::<RulesetUniform>() as _,
                             ),
                         },
                         count: None,
@@ -354,8 +1577,7 @@ impl State {
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
-                            // min_binding_size: None,
-                            min_binding_size: wgpu::BufferSize::new((total_cells * 16) as _),
+                            min_binding_size: None,
                         },
                         count: None,
                     },
@@ -365,8 +1587,7 @@ impl State {
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: false },
                             has_dynamic_offset: false,
-                            // min_binding_size: None,
-                            min_binding_size: wgpu::BufferSize::new((total_cells * 16) as _),
+                            min_binding_size: None,
                         },
                         count: None,
                     },
@@ -390,49 +1611,15 @@ impl State {
             entry_point: "main",
         });
 
-        // Setting up initial cell data data
-        // TODO: Fix duplicate code logic
-        let mut initial_cell_state: Vec<i32>;
-        if let Some(scene) = scene {
-            initial_cell_state = vec![0; total_cells as usize * 4];
-            let mut chunked_initial_cell_state = initial_cell_state.chunks_mut(4);
-            for x in 0..grid_width {
-                for y in 0..grid_width {
-                    for z in 0..grid_width {
-                        let cell_instance_chunk = chunked_initial_cell_state.next().unwrap();
-                        // cell_instance_chunk[0] = 1;
-                        cell_instance_chunk[1] = x as i32;
-                        cell_instance_chunk[2] = y as i32;
-                        cell_instance_chunk[3] = z as i32;
-                    }
-                }
-            }
-            for cube in scene.cubes.iter() {
-                let index = cube.x as usize
-                    + cube.y as usize * grid_width as usize
-                    + cube.z as usize * grid_width as usize * grid_width as usize;
-                initial_cell_state[index * 4] = 1;
-            }
-        } else {
-            let mut rng = WyRand::new();
-            initial_cell_state = (0..(total_cells * 4) as usize)
-                .map(|_| if rng.generate::<f32>() > 0.1 { 0 } else { 1 })
-                .collect();
-
-            let mut chunked_initial_cell_state = initial_cell_state.chunks_mut(4);
-            for x in 0..grid_width {
-                for y in 0..grid_width {
-                    for z in 0..grid_width {
-                        let cell_instance_chunk = chunked_initial_cell_state.next().unwrap();
-                        // cell_instance_chunk[0] = 1;
-                        cell_instance_chunk[1] = x as i32;
-                        cell_instance_chunk[2] = y as i32;
-                        cell_instance_chunk[3] = z as i32;
-                    }
-                }
-            }
-        }
+        (compute_bind_group_layout, compute_pipeline)
+    }
 
+    fn setup_cell_buffers(
+        device: &wgpu::Device,
+        compute_bind_group_layout: &wgpu::BindGroupLayout,
+        ruleset_buffer: &wgpu::Buffer,
+        initial_cell_state: &[Cell],
+    ) -> (Vec<wgpu::BindGroup>, Vec<wgpu::Buffer>) {
         // Create two buffers of cell state
         let mut cell_buffers = Vec::<wgpu::Buffer>::new();
         let mut cell_bind_groups = Vec::<wgpu::BindGroup>::new();
@@ -440,7 +1627,7 @@ impl State {
             cell_buffers.push(
                 device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some(&format!("Cell Buffer {}", i)),
-                    contents: bytemuck::cast_slice(&initial_cell_state),
+                    contents: bytemuck::cast_slice(initial_cell_state),
                     usage: wgpu::BufferUsages::VERTEX
                         | wgpu::BufferUsages::STORAGE
                         | wgpu::BufferUsages::COPY_DST
@@ -452,11 +1639,11 @@ impl State {
         // Create 2 bind groups one for each buffer
         for i in 0..2 {
             cell_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &compute_bind_group_layout,
+                layout: compute_bind_group_layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: rulset_buffer.as_entire_binding(),
+                        resource: ruleset_buffer.as_entire_binding(),
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
@@ -471,23 +1658,158 @@ impl State {
             }));
         }
 
-        (cell_bind_groups, cell_buffers, compute_pipeline)
+        (cell_bind_groups, cell_buffers)
+    }
+
+    fn setup_cell_render_bind_group_layout(
+        device: &wgpu::Device,
+        color_scheme: &ColorScheme,
+        grid_width: u32,
+        editor: &Editor,
+    ) -> (
+        wgpu::BindGroupLayout,
+        wgpu::Buffer,
+        wgpu::Buffer,
+        wgpu::Buffer,
+    ) {
+        let transition_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transition Buffer"),
+            contents: bytemuck::cast_slice(&[1f32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let highlight_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Highlight Buffer"),
+            contents: bytemuck::cast_slice(&[editor.to_uniform()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Buffer"),
+            contents: bytemuck::cast_slice(&[color_scheme.to_uniform(grid_width)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let cell_render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("cell_render_bind_group_layout"),
+            });
+
+        (
+            cell_render_bind_group_layout,
+            color_buffer,
+            transition_buffer,
+            highlight_buffer,
+        )
+    }
+
+    fn setup_cell_render_bind_groups(
+        device: &wgpu::Device,
+        cell_render_bind_group_layout: &wgpu::BindGroupLayout,
+        cell_buffers: &[wgpu::Buffer],
+        color_buffer: &wgpu::Buffer,
+        transition_buffer: &wgpu::Buffer,
+        highlight_buffer: &wgpu::Buffer,
+    ) -> Vec<wgpu::BindGroup> {
+        // One bind group per cell buffer holding the current generation, with
+        // the opposite buffer holding the previous one
+        (0..2)
+            .map(|i| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: cell_render_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: color_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: cell_buffers[i].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: cell_buffers[(i + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: transition_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: highlight_buffer.as_entire_binding(),
+                        },
+                    ],
+                    label: Some("cell_render_bind_group"),
+                })
+            })
+            .collect()
     }
 
     fn setup_render_pipeline(
         device: &wgpu::Device,
         shader: &wgpu::ShaderModule,
-        config: &wgpu::SurfaceConfiguration,
         camera: &Camera,
-        total_cells: u32,
+        cell_render_bind_group_layout: &wgpu::BindGroupLayout,
+        shadow_bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
     ) -> (
         wgpu::BindGroup,
         wgpu::Buffer,
         CameraUniform,
         wgpu::PipelineLayout,
         wgpu::RenderPipeline,
-        wgpu::Buffer,
-        wgpu::Buffer,
     ) {
         // Camera Logic!!!
         let mut camera_uniform = CameraUniform::new();
@@ -526,13 +1848,25 @@ impl State {
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&camera_bind_group_layout],
+                bind_group_layouts: &[&camera_bind_group_layout, cell_render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // The lit cubes also sample the shadow map
+        let shaded_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shaded Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    cell_render_bind_group_layout,
+                    shadow_bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(&shaded_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: shader,
                 entry_point: "vs_main",
@@ -541,14 +1875,7 @@ impl State {
             fragment: Some(wgpu::FragmentState {
                 module: shader,
                 entry_point: "fs_main",
-                targets: &[wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }],
+                targets: &bloom::scene_targets(wgpu::BlendState::REPLACE, true),
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -571,7 +1898,7 @@ impl State {
                 bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -580,28 +1907,80 @@ impl State {
             multiview: None,
         });
 
+        (
+            camera_bind_group,
+            camera_buffer,
+            camera_uniform,
+            render_pipeline_layout,
+            render_pipeline,
+        )
+    }
+
+    /// Sphere impostors for the points render mode, sharing the cube pipeline's bind groups.
+    fn setup_points_pipeline(
+        device: &wgpu::Device,
+        render_pipeline_layout: &wgpu::PipelineLayout,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Points Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("color.wgsl"), include_str!("points.wgsl")).into(),
+            ),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Points Pipeline"),
+            layout: Some(render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                // Everything comes from the cell buffer
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &bloom::scene_targets(wgpu::BlendState::REPLACE, true),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // The squares always face the camera
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+    }
+
+    /// Room for `cubes` cubes, sized from the live cells rather than the whole
+    /// grid since every cube takes over 800 bytes.
+    fn setup_mesh_buffers(device: &wgpu::Device, cubes: usize) -> (wgpu::Buffer, wgpu::Buffer) {
         let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Vertex Buffer"),
-            size: (total_cells as wgpu::BufferAddress) * 4 * 6 * 8, // 3 f32 pos, 3 f32 color, 8 verteces per cell
+            size: (cubes * 24 * mem::size_of::<Vertex>()) as wgpu::BufferAddress, // 24 verteces per cube
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Index Buffer"),
-            size: (total_cells as wgpu::BufferAddress) * 4 * 36, // u32, 36 indcies per cell
+            size: (cubes * 36 * mem::size_of::<u32>()) as wgpu::BufferAddress, // 36 indcies per cube
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        (
-            camera_bind_group,
-            camera_buffer,
-            camera_uniform,
-            render_pipeline_layout,
-            render_pipeline,
-            vertex_buffer,
-            index_buffer,
-        )
+        (vertex_buffer, index_buffer)
     }
 }
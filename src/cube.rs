@@ -1,5 +1,20 @@
 use crate::scene::Vertex;
 
+// Each face is described by its outward normal and the two in-plane axes
+// (u, v), ordered so that u x v = normal. Walking the corners below in order
+// therefore winds counter-clockwise when seen from outside the cube.
+#[rustfmt::skip]
+const FACES: [([i32; 3], [i32; 3], [i32; 3]); 6] = [
+    ([ 1,  0,  0], [0, 1, 0], [0, 0, 1]),
+    ([-1,  0,  0], [0, 0, 1], [0, 1, 0]),
+    ([ 0,  1,  0], [0, 0, 1], [1, 0, 0]),
+    ([ 0, -1,  0], [1, 0, 0], [0, 0, 1]),
+    ([ 0,  0,  1], [1, 0, 0], [0, 1, 0]),
+    ([ 0,  0, -1], [0, 1, 0], [1, 0, 0]),
+];
+
+const CORNERS: [(i32, i32); 4] = [(0, 0), (1, 0), (1, 1), (0, 1)];
+
 pub struct Cube {
     pub x: f32,
     pub y: f32,
@@ -9,36 +24,59 @@ pub struct Cube {
 }
 
 impl Cube {
-    pub fn new(x: f32, y: f32, z: f32, width: f32, color: [f32; 3]) -> Self {
-        let mut vertices = Vec::new();
-
-        for dx in 0..2 {
-            for dy in 0..2 {
-                for dz in 0..2 {
-                    let vx = x + dx as f32 * width;
-                    let vy = y + dy as f32 * width;
-                    let vz = z + dz as f32 * width;
-                    let position = [vx, vy, vz];
-                    vertices.push(Vertex { position, color })
+    pub fn new(x: f32, y: f32, z: f32, width: f32) -> Self {
+        Self::new_occluded(x, y, z, width, |_, _, _| false)
+    }
+
+    /// Builds a cube with per-vertex ambient occlusion. `is_solid` is asked
+    /// about the grid cells surrounding this one and should return true for
+    /// live cells.
+    pub fn new_occluded(
+        x: f32,
+        y: f32,
+        z: f32,
+        width: f32,
+        is_solid: impl Fn(i32, i32, i32) -> bool,
+    ) -> Self {
+        let origin = [x, y, z];
+        let cell = [x as i32, y as i32, z as i32];
+        let mut vertices = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+
+        for (normal, u, v) in FACES {
+            // Cell in front of this face, shifted su along u and sv along v
+            let neighbour = |su: i32, sv: i32| {
+                let n = |k: usize| cell[k] + normal[k] + su * u[k] + sv * v[k];
+                is_solid(n(0), n(1), n(2))
+            };
+
+            let base = vertices.len() as u32;
+            let mut ao = [0.; 4];
+            for (i, &(cu, cv)) in CORNERS.iter().enumerate() {
+                let (su, sv) = (cu * 2 - 1, cv * 2 - 1);
+                ao[i] = vertex_ao(neighbour(su, 0), neighbour(0, sv), neighbour(su, sv));
+
+                let mut position = [0.; 3];
+                for k in 0..3 {
+                    let offset = normal[k].max(0) + cu * u[k] + cv * v[k];
+                    position[k] = origin[k] + offset as f32 * width;
                 }
+                vertices.push(Vertex {
+                    position,
+                    ao: ao[i],
+                    cell: cell.map(|n| n as u32),
+                });
             }
-        }
 
-        #[rustfmt::skip]
-        let indices = vec![
-            0, 2, 3,
-            0, 3, 1,
-            0, 6, 2,
-            0, 4, 6,
-            0, 1, 5,
-            0, 5, 4,
-            7, 3, 2,
-            7, 2, 6,
-            7, 4, 5,
-            7, 6, 4,
-            7, 5, 1,
-            7, 1, 3
-        ];
+            // Split the quad along the brighter diagonal, otherwise the
+            // occlusion gets interpolated anisotropically across the face.
+            let quad = if ao[0] + ao[2] > ao[1] + ao[3] {
+                [0, 1, 2, 0, 2, 3]
+            } else {
+                [1, 2, 3, 1, 3, 0]
+            };
+            indices.extend(quad.iter().map(|i| base + i));
+        }
 
         Self {
             x,
@@ -49,3 +87,11 @@ impl Cube {
         }
     }
 }
+
+/// Classic voxel AO: 1.0 is fully lit, 0.0 is a corner boxed in by both sides.
+fn vertex_ao(side1: bool, side2: bool, corner: bool) -> f32 {
+    if side1 && side2 {
+        return 0.;
+    }
+    (3 - side1 as u32 - side2 as u32 - corner as u32) as f32 / 3.
+}
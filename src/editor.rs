@@ -0,0 +1,250 @@
+use std::{collections::HashSet, fmt};
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::{
+    cell::Cell,
+    clip::Clip,
+    picking::{self, Hit, Ray},
+};
+
+/// Largest brush radius in cells
+const MAX_RADIUS: i32 = 8;
+
+/// What a click does to the cell under the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    /// Flips the hovered cell between dead and alive
+    Toggle,
+    /// Adds a cell against the hovered face
+    Place,
+    /// Adds cells in the brush while dragging over the surface
+    Paint,
+    /// Kills cells in the brush while dragging
+    Erase,
+}
+
+impl Tool {
+    pub fn next(self) -> Self {
+        match self {
+            Tool::Toggle => Tool::Place,
+            Tool::Place => Tool::Paint,
+            Tool::Paint => Tool::Erase,
+            Tool::Erase => Tool::Toggle,
+        }
+    }
+
+    fn uses_brush(self) -> bool {
+        matches!(self, Tool::Paint | Tool::Erase)
+    }
+}
+
+// Order must match the shapes in shader.wgsl
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    Sphere,
+    Cube,
+}
+
+impl BrushShape {
+    pub fn next(self) -> Self {
+        match self {
+            BrushShape::Sphere => BrushShape::Cube,
+            BrushShape::Cube => BrushShape::Sphere,
+        }
+    }
+
+    fn contains(self, offset: [i32; 3], radius: i32) -> bool {
+        match self {
+            BrushShape::Sphere => offset.iter().map(|n| n * n).sum::<i32>() <= radius * radius,
+            BrushShape::Cube => offset.iter().all(|n| n.abs() <= radius),
+        }
+    }
+}
+
+/// Drawing cells by hand while the simulation is paused.
+pub struct Editor {
+    pub enabled: bool,
+    pub tool: Tool,
+    pub shape: BrushShape,
+    pub radius: i32,
+    cursor: Option<PhysicalPosition<f64>>,
+    hover: Option<Hit>,
+    // Cells painted since the button went down. Picking looks through them,
+    // so a stroke spreads over the surface instead of piling up towards the camera.
+    stroke: Option<HashSet<usize>>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            tool: Tool::Toggle,
+            shape: BrushShape::Sphere,
+            radius: 1,
+            cursor: None,
+            hover: None,
+            stroke: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.stroke = None;
+    }
+
+    pub fn resize_brush(&mut self, change: i32) {
+        self.radius = (self.radius + change).clamp(0, MAX_RADIUS);
+    }
+
+    pub fn set_cursor(&mut self, cursor: PhysicalPosition<f64>) {
+        self.cursor = Some(cursor);
+    }
+
+    /// Finds the cell under the cursor.
+    pub fn pick(
+        &mut self,
+        view_proj: cgmath::Matrix4<f32>,
+        size: PhysicalSize<u32>,
+        grid_width: u32,
+        cells: &[Cell],
+        clip: &Clip,
+    ) {
+        let ray = match self.cursor {
+            Some(cursor) if self.enabled => Ray::from_cursor(view_proj, cursor, size),
+            _ => None,
+        };
+        let stroke = &self.stroke;
+        self.hover = ray.and_then(|ray| {
+            picking::cast(&ray, grid_width, |[x, y, z]| {
+                // Hidden cells can't be picked
+                clip.contains(x, y, z)
+                    && Cell::index(x, y, z, grid_width).is_some_and(|i| {
+                        cells[i].is_solid() && !stroke.as_ref().is_some_and(|s| s.contains(&i))
+                    })
+            })
+        });
+    }
+
+    /// Edits for the button going down, as cell indices and their new states.
+    pub fn press(&mut self, grid_width: u32, cells: &[Cell]) -> Vec<(usize, i32)> {
+        let hit = match self.hover {
+            Some(hit) if self.enabled => hit,
+            _ => return Vec::new(),
+        };
+        match self.tool {
+            Tool::Toggle => {
+                let [x, y, z] = hit.cell;
+                Cell::index(x, y, z, grid_width)
+                    .map(|i| {
+                        let state = if cells[i].is_solid() {
+                            Cell::DEAD
+                        } else {
+                            Cell::ALIVE
+                        };
+                        vec![(i, state)]
+                    })
+                    .unwrap_or_default()
+            }
+            Tool::Place => {
+                let [x, y, z] = hit.adjacent();
+                Cell::index(x, y, z, grid_width)
+                    .map(|i| vec![(i, Cell::ALIVE)])
+                    .unwrap_or_default()
+            }
+            Tool::Paint | Tool::Erase => {
+                self.stroke = Some(HashSet::new());
+                self.drag(grid_width, cells)
+            }
+        }
+    }
+
+    /// Edits for the cursor moving with the button down, only brushes paint as they go.
+    pub fn drag(&mut self, grid_width: u32, cells: &[Cell]) -> Vec<(usize, i32)> {
+        let (hit, stroke) = match (self.hover, &mut self.stroke) {
+            (Some(hit), Some(stroke)) if self.enabled => (hit, stroke),
+            _ => return Vec::new(),
+        };
+        let (centre, state) = match self.tool {
+            Tool::Paint => (hit.adjacent(), Cell::ALIVE),
+            Tool::Erase if !hit.is_floor() => (hit.cell, Cell::DEAD),
+            _ => return Vec::new(),
+        };
+
+        let r = self.radius;
+        let mut edits = Vec::new();
+        for dx in -r..=r {
+            for dy in -r..=r {
+                for dz in -r..=r {
+                    if !self.shape.contains([dx, dy, dz], r) {
+                        continue;
+                    }
+                    let [x, y, z] = centre;
+                    if let Some(i) = Cell::index(x + dx, y + dy, z + dz, grid_width) {
+                        if cells[i].is_solid() != (state != Cell::DEAD) {
+                            stroke.insert(i);
+                            edits.push((i, state));
+                        }
+                    }
+                }
+            }
+        }
+        edits
+    }
+
+    pub fn release(&mut self) {
+        self.stroke = None;
+    }
+
+    pub fn to_uniform(&self) -> HighlightUniform {
+        let (cell, active) = match self.hover {
+            Some(hit) if self.enabled => (hit.cell, 1),
+            _ => ([0; 3], 0),
+        };
+        HighlightUniform {
+            cell,
+            radius: if self.tool.uses_brush() {
+                self.radius
+            } else {
+                0
+            },
+            shape: self.shape as u32,
+            active,
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl fmt::Display for Editor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.tool)?;
+        if self.tool.uses_brush() {
+            write!(f, " {:?} radius {}", self.shape, self.radius)?;
+        }
+        Ok(())
+    }
+}
+
+// Layout must match `Highlight` in shader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct HighlightUniform {
+    cell: [i32; 3],
+    radius: i32,
+    shape: u32,
+    active: u32,
+    _padding: [u32; 2],
+}
+
+impl HighlightUniform {
+    /// Just the one cell, whatever the brush.
+    pub fn cell(cell: [i32; 3]) -> Self {
+        Self {
+            cell,
+            radius: 0,
+            shape: 0,
+            active: 1,
+            _padding: [0; 2],
+        }
+    }
+}
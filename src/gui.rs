@@ -0,0 +1,222 @@
+use egui::{epaint::ClippedPrimitive, TexturesDelta};
+use egui_wgpu::renderer::{RenderPass, ScreenDescriptor};
+use winit::{event::WindowEvent, window::Window};
+
+use crate::{
+    clip::{Axis, Clip},
+    color::ColorMode,
+    rule::{Boundary, Rule, NEIGHBOUR_COUNTS},
+};
+
+/// Largest grid offered, the mesh buffers grow with the cube of the width
+const MAX_GRID_WIDTH: u32 = 64;
+const MIN_GRID_WIDTH: u32 = 4;
+
+/// Simulation settings the panel edits. They're copied out of the state
+/// each frame and whatever changed gets applied back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Controls {
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub gps: f32,
+    pub min_gps: f32,
+    pub max_gps: f32,
+    pub color_mode: ColorMode,
+    pub density: f32,
+    pub seed: u64,
+    pub clip: Clip,
+}
+
+/// Buttons that start the simulation over.
+pub enum Action {
+    /// From the current seed, resizing the grid to this width
+    Reset { grid_width: u32 },
+    /// From a new seed, resizing the grid to this width
+    Randomise { grid_width: u32 },
+}
+
+/// Settings panel drawn over the scene with egui.
+pub struct Gui {
+    context: egui::Context,
+    winit_state: egui_winit::State,
+    render_pass: RenderPass,
+    visible: bool,
+    // Only applied on reset since it rebuilds the grid
+    grid_width: u32,
+    output: Option<(Vec<ClippedPrimitive>, TexturesDelta)>,
+}
+
+impl Gui {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        window: &Window,
+        grid_width: u32,
+    ) -> Self {
+        let max_texture_side = device.limits().max_texture_dimension_2d as usize;
+        Self {
+            context: egui::Context::default(),
+            winit_state: egui_winit::State::new(max_texture_side, window),
+            render_pass: RenderPass::new(device, format, 1),
+            visible: true,
+            grid_width,
+            output: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Returns true when the panel used the event, e.g. a click on a
+    /// checkbox or typing in a field, so it shouldn't reach the camera.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        self.visible && self.winit_state.on_event(&self.context, event)
+    }
+
+    /// Lays out the panel for this frame, editing `controls` in place.
+    pub fn run(&mut self, window: &Window, controls: &mut Controls) -> Option<Action> {
+        if !self.visible {
+            self.output = None;
+            return None;
+        }
+
+        let mut action = None;
+        let grid_width = &mut self.grid_width;
+        let raw_input = self.winit_state.take_egui_input(window);
+        let full_output = self.context.run(raw_input, |context| {
+            egui::Window::new("Simulation").show(context, |ui| {
+                counts_grid(ui, "Birth", &mut controls.rule.birth);
+                counts_grid(ui, "Survive", &mut controls.rule.survive);
+                ui.label(controls.rule.to_string());
+                ui.separator();
+
+                ui.add(
+                    egui::Slider::new(&mut controls.gps, controls.min_gps..=controls.max_gps)
+                        .logarithmic(true)
+                        .text("Generations per second"),
+                );
+                egui::ComboBox::from_label("Colour mode")
+                    .selected_text(format!("{:?}", controls.color_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in ColorMode::ALL {
+                            ui.selectable_value(
+                                &mut controls.color_mode,
+                                mode,
+                                format!("{:?}", mode),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Boundary")
+                    .selected_text(format!("{:?}", controls.boundary))
+                    .show_ui(ui, |ui| {
+                        for boundary in Boundary::ALL {
+                            ui.selectable_value(
+                                &mut controls.boundary,
+                                boundary,
+                                format!("{:?}", boundary),
+                            );
+                        }
+                    });
+                ui.separator();
+
+                clip_controls(ui, &mut controls.clip);
+                ui.separator();
+
+                ui.label("Applied on reset");
+                ui.add(
+                    egui::Slider::new(grid_width, MIN_GRID_WIDTH..=MAX_GRID_WIDTH.max(*grid_width))
+                        .text("Grid width"),
+                );
+                ui.add(egui::Slider::new(&mut controls.density, 0.0..=1.0).text("Density"));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut controls.seed));
+                    ui.label("Seed");
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        action = Some(Action::Reset {
+                            grid_width: *grid_width,
+                        });
+                    }
+                    if ui.button("Randomise").clicked() {
+                        action = Some(Action::Randomise {
+                            grid_width: *grid_width,
+                        });
+                    }
+                });
+            });
+        });
+
+        self.winit_state
+            .handle_platform_output(window, &self.context, full_output.platform_output);
+        let paint_jobs = self.context.tessellate(full_output.shapes);
+        self.output = Some((paint_jobs, full_output.textures_delta));
+        action
+    }
+
+    /// Draws the panel laid out by the last `run` on top of `target`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let (paint_jobs, textures_delta) = match self.output.take() {
+            Some(output) => output,
+            None => return,
+        };
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: self.winit_state.pixels_per_point(),
+        };
+
+        for (id, image_delta) in textures_delta.set.iter() {
+            self.render_pass
+                .update_texture(device, queue, *id, image_delta);
+        }
+        self.render_pass
+            .update_buffers(device, queue, &paint_jobs, &screen_descriptor);
+        // No clear colour, so it's drawn over the scene
+        self.render_pass
+            .execute(encoder, target, &paint_jobs, &screen_descriptor, None);
+        for id in textures_delta.free.iter() {
+            self.render_pass.free_texture(id);
+        }
+    }
+}
+
+/// Picks the axis and layer to slice the grid at.
+fn clip_controls(ui: &mut egui::Ui, clip: &mut Clip) {
+    egui::ComboBox::from_label("Slice axis")
+        .selected_text(format!("{:?}", clip.axis))
+        .show_ui(ui, |ui| {
+            for axis in Axis::ALL {
+                ui.selectable_value(&mut clip.axis, axis, format!("{:?}", axis));
+            }
+        });
+    let mut layer = clip.layer();
+    let label = if clip.single_layer {
+        "Layer"
+    } else {
+        "Last visible layer"
+    };
+    ui.add(egui::Slider::new(&mut layer, 0..=clip.grid_width() - 1).text(label));
+    clip.set_layer(layer);
+    ui.checkbox(&mut clip.single_layer, "Single layer");
+}
+
+/// Checkboxes for each neighbour count, nine to a row.
+fn counts_grid(ui: &mut egui::Ui, label: &str, counts: &mut [bool; NEIGHBOUR_COUNTS]) {
+    ui.label(label);
+    egui::Grid::new(label).show(ui, |ui| {
+        for (count, checked) in counts.iter_mut().enumerate() {
+            ui.checkbox(checked, count.to_string());
+            if count % 9 == 8 {
+                ui.end_row();
+            }
+        }
+    });
+}
@@ -0,0 +1,315 @@
+use std::{borrow::Cow, mem};
+
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    bloom,
+    camera::{Camera, OPENGL_TO_WGPU_MATRIX},
+    rule::Boundary,
+    texture::Texture,
+};
+
+const DEAD_BOX_COLOR: [f32; 3] = [0.6, 0.6, 0.65];
+// Stands out so it's clear cells crossing the edge come back on the other side
+const WRAP_BOX_COLOR: [f32; 3] = [0.3, 0.8, 0.9];
+const FLOOR_COLOR: [f32; 3] = [0.25, 0.25, 0.3];
+/// Keeps the lines just outside the cubes on the edge of the grid
+const INSET: f32 = -0.02;
+
+/// Side of the gizmo as a fraction of the window's shorter side
+const GIZMO_FRACTION: f32 = 0.12;
+/// Gap from the window corner, in pixels
+const MARGIN: f32 = 8.;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl LineVertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+/// Visual reference for where the grid is: its bounding box, coloured by the
+/// boundary, optional floor lines and an XYZ axis gizmo in the bottom left.
+pub struct Guides {
+    pipeline: wgpu::RenderPipeline,
+    gizmo_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    gizmo_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    gizmo_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    gizmo_vertex_buffer: wgpu::Buffer,
+    /// Cells between floor lines, none for no floor lines
+    floor_spacing: Option<u32>,
+    pub visible: bool,
+}
+
+impl Guides {
+    pub fn new(
+        device: &wgpu::Device,
+        grid_width: u32,
+        boundary: Boundary,
+        floor_spacing: Option<u32>,
+        visible: bool,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Guides Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("guides.wgsl"))),
+        });
+
+        // Written by `write_uniform` before anything is drawn
+        let create_uniform_buffer = |label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let uniform_buffer = create_uniform_buffer("Guides Buffer");
+        let gizmo_buffer = create_uniform_buffer("Gizmo Buffer");
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("guides_bind_group_layout"),
+        });
+        let create_bind_group = |buffer: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: Some("guides_bind_group"),
+            })
+        };
+        let bind_group = create_bind_group(&uniform_buffer);
+        let gizmo_bind_group = create_bind_group(&gizmo_buffer);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Guides Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The gizmo is drawn over everything, the rest is hidden behind cells
+        let create_pipeline = |label, depth_write_enabled, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[LineVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &bloom::scene_targets(wgpu::BlendState::REPLACE, false),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("Guides Pipeline", true, wgpu::CompareFunction::Less);
+        let gizmo_pipeline =
+            create_pipeline("Gizmo Pipeline", false, wgpu::CompareFunction::Always);
+
+        let axis = |k: usize| {
+            let mut end = [0.; 3];
+            end[k] = 1.;
+            let mut color = [0.2; 3];
+            color[k] = 1.;
+            [
+                LineVertex {
+                    position: [0.; 3],
+                    color,
+                },
+                LineVertex {
+                    position: end,
+                    color,
+                },
+            ]
+        };
+        let gizmo_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Gizmo Vertex Buffer"),
+            contents: bytemuck::cast_slice(&[axis(0), axis(1), axis(2)]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let vertices = Self::grid_lines(grid_width, boundary, floor_spacing);
+        Self {
+            pipeline,
+            gizmo_pipeline,
+            uniform_buffer,
+            gizmo_buffer,
+            bind_group,
+            gizmo_bind_group,
+            vertex_buffer: Self::create_vertex_buffer(device, &vertices),
+            vertex_count: vertices.len() as u32,
+            gizmo_vertex_buffer,
+            floor_spacing,
+            visible,
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, vertices: &[LineVertex]) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Guides Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    }
+
+    /// Pairs of vertices for the bounding box edges and floor lines.
+    fn grid_lines(
+        grid_width: u32,
+        boundary: Boundary,
+        floor_spacing: Option<u32>,
+    ) -> Vec<LineVertex> {
+        let (low, high) = (INSET, grid_width as f32 - INSET);
+        let mut vertices = Vec::new();
+        let mut line = |from: [f32; 3], to: [f32; 3], color: [f32; 3]| {
+            vertices.push(LineVertex {
+                position: from,
+                color,
+            });
+            vertices.push(LineVertex {
+                position: to,
+                color,
+            });
+        };
+
+        let box_color = match boundary {
+            Boundary::Dead => DEAD_BOX_COLOR,
+            Boundary::Wrap => WRAP_BOX_COLOR,
+        };
+        // Each edge runs along axis k, from one of the four corners of the other two
+        for k in 0..3 {
+            for corner in 0..4 {
+                let mut from = [0.; 3];
+                let mut to = [0.; 3];
+                let (u, v) = ((k + 1) % 3, (k + 2) % 3);
+                from[u] = if corner & 1 == 0 { low } else { high };
+                from[v] = if corner & 2 == 0 { low } else { high };
+                to[u] = from[u];
+                to[v] = from[v];
+                from[k] = low;
+                to[k] = high;
+                line(from, to, box_color);
+            }
+        }
+
+        if let Some(spacing) = floor_spacing {
+            for n in (spacing..grid_width).step_by(spacing as usize) {
+                let n = n as f32;
+                line([n, low, low], [n, low, high], FLOOR_COLOR);
+                line([low, low, n], [high, low, n], FLOOR_COLOR);
+            }
+        }
+        vertices
+    }
+
+    /// Rebuilds the lines after the grid has been resized or its boundary changed.
+    pub fn set_grid(&mut self, device: &wgpu::Device, grid_width: u32, boundary: Boundary) {
+        let vertices = Self::grid_lines(grid_width, boundary, self.floor_spacing);
+        self.vertex_buffer = Self::create_vertex_buffer(device, &vertices);
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Keeps the lines following the camera, the gizmo only turns with it.
+    pub fn write_uniform(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let view_proj: [[f32; 4]; 4] = camera.build_view_projection_matrix().into();
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[view_proj]));
+
+        let mut rotation = camera.build_view_matrix();
+        rotation.w = cgmath::Vector4::unit_w();
+        let proj = cgmath::ortho(-1.2, 1.2, -1.2, 1.2, -2., 2.);
+        let gizmo: [[f32; 4]; 4] = (OPENGL_TO_WGPU_MATRIX * proj * rotation).into();
+        queue.write_buffer(&self.gizmo_buffer, 0, bytemuck::cast_slice(&[gizmo]));
+    }
+
+    /// Draws the bounding box and floor, depth tested against the cells.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+
+    /// Draws the gizmo over everything in the bottom left corner, this should
+    /// come last as it changes the viewport.
+    pub fn draw_gizmo<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        size: PhysicalSize<u32>,
+    ) {
+        let side = (size.width.min(size.height) as f32 * GIZMO_FRACTION).floor();
+        if side < 1. {
+            return;
+        }
+        render_pass.set_viewport(
+            MARGIN,
+            size.height as f32 - side - MARGIN,
+            side,
+            side,
+            0.,
+            1.,
+        );
+        render_pass.set_pipeline(&self.gizmo_pipeline);
+        render_pass.set_bind_group(0, &self.gizmo_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.gizmo_vertex_buffer.slice(..));
+        render_pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::{core::State, offscreen::Resolution, settings::Settings};
+
+/// Renders `steps` generations after the first to numbered PNGs without
+/// opening a window. `pattern` is a path with a printf style frame number,
+/// e.g. "out/%05d.png". Each frame is one generation, and the camera moves
+/// as if the frames were played back at the simulation speed.
+pub fn render_frames(
+    pattern: &str,
+    steps: u32,
+    resolution: Resolution,
+    settings: Settings,
+) -> anyhow::Result<()> {
+    env_logger::init();
+    // Check the pattern before doing any work
+    frame_path(pattern, 0)?;
+
+    let dt = 1. / settings.gps;
+    let size = winit::dpi::PhysicalSize::new(resolution.width, resolution.height);
+    let mut state = pollster::block_on(State::new(None, size, None, settings))?;
+    state.play_camera_path();
+
+    for frame in 0..=steps {
+        if frame > 0 {
+            state.step(1);
+            // The last frame's transition was finished, so the camera path
+            // would otherwise be sampled a generation ahead
+            state.start_transition();
+        }
+        state.update_camera(dt);
+        state.finish_transition();
+
+        let image = pollster::block_on(state.render_to_image(resolution, false))?;
+        let path = frame_path(pattern, frame)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Couldn't create {}", parent.display()))?;
+        }
+        image
+            .save(&path)
+            .with_context(|| format!("Couldn't save {}", path.display()))?;
+        println!("Saved {}", path.display());
+    }
+    Ok(())
+}
+
+/// Fills in the first %d or %0Nd in `pattern` with the frame number.
+fn frame_path(pattern: &str, frame: u32) -> anyhow::Result<PathBuf> {
+    let error = || anyhow!("{} needs a frame number like %05d in it", pattern);
+    let start = pattern.find('%').ok_or_else(error)?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d').ok_or_else(error)?;
+    let spec = &rest[..end];
+
+    let number = if spec.is_empty() {
+        frame.to_string()
+    } else {
+        let width: usize = spec
+            .strip_prefix('0')
+            .unwrap_or(spec)
+            .parse()
+            .map_err(|_| error())?;
+        format!("{:0width$}", frame, width = width)
+    };
+    Ok(PathBuf::from(format!(
+        "{}{}{}",
+        &pattern[..start],
+        number,
+        &rest[end + 1..]
+    )))
+}
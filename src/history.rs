@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+
+use crate::cell::Cell;
+
+/// A past generation, stored as just its non-dead cells.
+pub struct Snapshot {
+    pub generation: usize,
+    cells: Vec<Cell>,
+}
+
+impl Snapshot {
+    /// Expands back out into a full grid ready to upload to a cell buffer.
+    pub fn to_cells(&self, grid_width: u32) -> Vec<Cell> {
+        let mut cells = Cell::new_grid(grid_width);
+        for cell in self.cells.iter() {
+            let index = Cell::index(cell.x, cell.y, cell.z, grid_width).unwrap();
+            cells[index] = *cell;
+        }
+        cells
+    }
+}
+
+/// Bounded ring of recent generations to rewind through. Only generations
+/// that were read back for drawing get recorded, so when several steps run
+/// in one frame rewinding skips over the ones in between.
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, generation: usize, cells: &[Cell]) {
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|snapshot| snapshot.generation >= generation)
+        {
+            return;
+        }
+
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot {
+            generation,
+            cells: cells
+                .iter()
+                .filter(|cell| cell.is_solid())
+                .copied()
+                .collect(),
+        });
+    }
+
+    /// Drops the latest generation and returns the one before it, if any is left.
+    pub fn rewind(&mut self) -> Option<&Snapshot> {
+        if self.snapshots.len() < 2 {
+            return None;
+        }
+        self.snapshots.pop_back();
+        self.snapshots.back()
+    }
+}
@@ -0,0 +1,239 @@
+use std::{borrow::Cow, mem};
+
+use wgpu::util::DeviceExt;
+
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// Screen pixels per font pixel
+const SCALE: f32 = 2.;
+/// Gap around the text and from the window corner, in screen pixels
+const MARGIN: f32 = 8.;
+/// Space taken by a character and a line, in font pixels
+const ADVANCE: usize = GLYPH_WIDTH + 1;
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 3;
+
+const TEXT_COLOR: [f32; 4] = [1., 1., 1., 1.];
+const BACKING_COLOR: [f32; 4] = [0., 0., 0., 0.5];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct HudVertex {
+    /// In pixels from the top left of the window
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl HudVertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<HudVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Lines of text in the top left corner, drawn from a built in bitmap font
+/// as one quad per lit pixel.
+pub struct Hud {
+    pipeline: wgpu::RenderPipeline,
+    screen_buffer: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+    vertex_count: u32,
+    pub visible: bool,
+}
+
+impl Hud {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("HUD Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("hud.wgsl"))),
+        });
+
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("HUD Screen Buffer"),
+            contents: bytemuck::cast_slice(&[0f32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let screen_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("hud_screen_bind_group_layout"),
+            });
+
+        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &screen_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_buffer.as_entire_binding(),
+            }],
+            label: Some("hud_screen_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("HUD Pipeline Layout"),
+            bind_group_layouts: &[&screen_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("HUD Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[HudVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            screen_buffer,
+            screen_bind_group,
+            vertex_buffer: Self::create_vertex_buffer(device, 0),
+            vertex_capacity: 0,
+            vertex_count: 0,
+            visible: false,
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, vertex_count: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("HUD Vertex Buffer"),
+            // Never empty, wgpu doesn't allow zero sized vertex buffers
+            size: (vertex_count.max(6) * mem::size_of::<HudVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Lays out `lines` for a window of the given size, growing the vertex
+    /// buffer if the text needs more room.
+    pub fn set_text(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lines: &[String],
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let mut vertices = Vec::new();
+
+        // Darken behind the text so it reads over bright cells
+        let columns = lines.iter().map(|line| line.chars().count()).max();
+        let text_width = (columns.unwrap_or(0) * ADVANCE) as f32 * SCALE;
+        let text_height = (lines.len() * LINE_HEIGHT) as f32 * SCALE;
+        push_quad(
+            &mut vertices,
+            [MARGIN, MARGIN],
+            [text_width + MARGIN * 2., text_height + MARGIN],
+            BACKING_COLOR,
+        );
+
+        for (row, line) in lines.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let origin = [
+                    MARGIN * 2. + (column * ADVANCE) as f32 * SCALE,
+                    MARGIN * 2. + (row * LINE_HEIGHT) as f32 * SCALE,
+                ];
+                for (y, glyph_row) in font::glyph(c).iter().enumerate() {
+                    for (x, pixel) in glyph_row.chars().enumerate() {
+                        if pixel == '#' {
+                            let position =
+                                [origin[0] + x as f32 * SCALE, origin[1] + y as f32 * SCALE];
+                            push_quad(&mut vertices, position, [SCALE, SCALE], TEXT_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+
+        if vertices.len() > self.vertex_capacity {
+            self.vertex_buffer = Self::create_vertex_buffer(device, vertices.len());
+            self.vertex_capacity = vertices.len();
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        queue.write_buffer(
+            &self.screen_buffer,
+            0,
+            bytemuck::cast_slice(&[size.width as f32, size.height as f32, 0., 0.]),
+        );
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    /// Draws the text laid out by the last `set_text` over `target`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("HUD Render Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+/// Two triangles covering `size` pixels from `position`.
+fn push_quad(vertices: &mut Vec<HudVertex>, position: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+    let [x0, y0] = position;
+    let [x1, y1] = [x0 + size[0], y0 + size[1]];
+    let corners = [[x0, y0], [x0, y1], [x1, y1], [x0, y0], [x1, y1], [x1, y0]];
+    vertices.extend(
+        corners
+            .into_iter()
+            .map(|position| HudVertex { position, color }),
+    );
+}
@@ -0,0 +1,35 @@
+// Flat coloured quads positioned in pixels, for the text overlay
+
+struct Screen {
+    size: vec2<f32>;
+    // Uniform buffers are padded out to 16 bytes
+    padding: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> screen: Screen;
+
+struct VertexInput {
+    [[location(0)]] position: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    // Pixels from the top left to clip space
+    let clip = in.position / screen.size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    out.clip_position = vec4<f32>(clip, 0.0, 1.0);
+    out.color = in.color;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return in.color;
+}
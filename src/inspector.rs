@@ -0,0 +1,236 @@
+use std::borrow::Cow;
+
+use wgpu::util::DeviceExt;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// Side of the viewport as a fraction of the window's shorter side
+const VIEWPORT_FRACTION: f32 = 0.35;
+/// Gap from the window corner, in pixels
+const MARGIN: f32 = 8.;
+
+// Layout must match `Slice` in inspector.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SliceUniform {
+    hover: [i32; 2],
+    grid_width: u32,
+    layer: u32,
+    _padding: [u32; 4],
+}
+
+/// Picture in picture view of one Z layer, a pixel per cell, drawn straight
+/// from the cell buffer in the bottom right corner.
+pub struct Inspector {
+    pipeline: wgpu::RenderPipeline,
+    slice_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_groups: Vec<wgpu::BindGroup>,
+    pub visible: bool,
+    pub layer: u32,
+    grid_width: u32,
+    hover: Option<[i32; 2]>,
+}
+
+impl Inspector {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        cell_buffers: &[wgpu::Buffer],
+        grid_width: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Inspector Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("inspector.wgsl"))),
+        });
+
+        let slice_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Inspector Slice Buffer"),
+            contents: bytemuck::cast_slice(&[SliceUniform {
+                hover: [-1; 2],
+                grid_width,
+                layer: 0,
+                _padding: [0; 4],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("inspector_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Inspector Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Inspector Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[format.into()],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let mut inspector = Self {
+            pipeline,
+            slice_buffer,
+            bind_group_layout,
+            bind_groups: Vec::new(),
+            visible: false,
+            layer: grid_width / 2,
+            grid_width,
+            hover: None,
+        };
+        inspector.set_cell_buffers(device, cell_buffers, grid_width);
+        inspector
+    }
+
+    /// Points at new cell buffers, after the grid has been resized.
+    pub fn set_cell_buffers(
+        &mut self,
+        device: &wgpu::Device,
+        cell_buffers: &[wgpu::Buffer],
+        grid_width: u32,
+    ) {
+        self.bind_groups = cell_buffers
+            .iter()
+            .map(|cell_buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.slice_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: cell_buffer.as_entire_binding(),
+                        },
+                    ],
+                    label: Some("inspector_bind_group"),
+                })
+            })
+            .collect();
+        self.grid_width = grid_width;
+        self.layer = self.layer.min(grid_width - 1);
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Moves through the layers, up for positive `lines`.
+    pub fn scroll(&mut self, lines: f32) {
+        let layer = self.layer as i32 + lines.round() as i32;
+        self.layer = layer.clamp(0, self.grid_width as i32 - 1) as u32;
+    }
+
+    /// Left, top and side of the square viewport in pixels.
+    fn viewport(&self, size: PhysicalSize<u32>) -> (f32, f32, f32) {
+        let side = (size.width.min(size.height) as f32 * VIEWPORT_FRACTION).floor();
+        (
+            size.width as f32 - side - MARGIN,
+            size.height as f32 - side - MARGIN,
+            side,
+        )
+    }
+
+    pub fn is_under(&self, cursor: PhysicalPosition<f64>, size: PhysicalSize<u32>) -> bool {
+        let (left, top, side) = self.viewport(size);
+        let (x, y) = (cursor.x as f32 - left, cursor.y as f32 - top);
+        self.visible && (0. ..side).contains(&x) && (0. ..side).contains(&y)
+    }
+
+    /// Finds the cell under the cursor, returning where it is in the grid.
+    pub fn hover(
+        &mut self,
+        cursor: Option<PhysicalPosition<f64>>,
+        size: PhysicalSize<u32>,
+    ) -> Option<[i32; 3]> {
+        let (left, top, side) = self.viewport(size);
+        self.hover = cursor
+            .filter(|&cursor| self.is_under(cursor, size))
+            .map(|cursor| {
+                let cell_size = side / self.grid_width as f32;
+                // The grid's y goes up the screen
+                let x = (cursor.x as f32 - left) / cell_size;
+                let y = (top + side - cursor.y as f32) / cell_size;
+                [x as i32, y as i32].map(|n| n.min(self.grid_width as i32 - 1))
+            });
+        self.hover.map(|[x, y]| [x, y, self.layer as i32])
+    }
+
+    /// Draws the current generation's layer over `target`.
+    pub fn draw(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        size: PhysicalSize<u32>,
+        generation: usize,
+    ) {
+        let slice = SliceUniform {
+            hover: self.hover.unwrap_or([-1; 2]),
+            grid_width: self.grid_width,
+            layer: self.layer,
+            _padding: [0; 4],
+        };
+        queue.write_buffer(&self.slice_buffer, 0, bytemuck::cast_slice(&[slice]));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Inspector Render Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let (left, top, side) = self.viewport(size);
+        if side < 1. {
+            return;
+        }
+        render_pass.set_viewport(left, top, side, side, 0., 1.);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_groups[generation % 2], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
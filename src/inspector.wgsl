@@ -0,0 +1,72 @@
+// Draws one Z layer of the grid as a pixel per cell
+
+struct Cell {
+    state      : i32;
+    x          : i32;
+    y          : i32;
+    z          : i32;
+    age        : i32;
+    neighbours : i32;
+};
+
+struct Cells {
+    cells : [[stride(24)]] array<Cell>;
+};
+
+struct Slice {
+    // Cell under the cursor, negative when there isn't one
+    hover: vec2<i32>;
+    grid_width: u32;
+    layer: u32;
+    padding: vec4<u32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> slice: Slice;
+[[group(0), binding(1)]]
+var<storage, read> cells: Cells;
+
+let DEAD_STATE: i32 = 0;
+let DEAD_COLOR: vec3<f32> = vec3<f32>(0.05, 0.05, 0.08);
+let ALIVE_COLOR: vec3<f32> = vec3<f32>(0.95, 0.95, 0.9);
+let HOVER_COLOR: vec3<f32> = vec3<f32>(1.0, 0.8, 0.2);
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    // From the bottom left of the viewport, y up like the grid
+    [[location(0)]] uv: vec2<f32>;
+};
+
+// One triangle covering the whole viewport
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32(vertex_index & 1u) * 4.0 - 1.0, f32(vertex_index >> 1u) * 4.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.uv = (position + 1.0) / 2.0;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let w = slice.grid_width;
+    let position = in.uv * f32(w);
+    let cell = min(vec2<u32>(position), vec2<u32>(w - 1u));
+    let state = cells.cells[slice.layer + cell.y * w + cell.x * w * w].state;
+
+    var color = DEAD_COLOR;
+    if (state != DEAD_STATE) {
+        color = ALIVE_COLOR;
+    }
+    if (all(vec2<i32>(cell) == slice.hover)) {
+        color = mix(color, HOVER_COLOR, 0.6);
+    }
+
+    // Lines between cells once they're a few pixels across
+    let pixel = fwidth(position.x);
+    let edge = min(fract(position), 1.0 - fract(position));
+    if (pixel < 0.25 && min(edge.x, edge.y) < pixel) {
+        color = mix(color, vec3<f32>(0.3), 0.5);
+    }
+    return vec4<f32>(color, 1.0);
+}
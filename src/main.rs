@@ -1,11 +1,44 @@
+use std::path::PathBuf;
+
+use bloom::BloomSettings;
+use camera::Turntable;
 use clap::Parser;
+use color::{ColorMode, ColorScheme, Opacity, Palette};
+use nanorand::{Rng, WyRand};
+use offscreen::Resolution;
+use recorder::GenerationRange;
+use rule::{Boundary, Rule};
+use settings::{AntiAliasing, RenderMode, Settings};
+use shadow::LightDirection;
+use volume::Transfer;
 use window::run;
 
+mod bloom;
 mod camera;
+mod cell;
+mod clip;
+mod color;
 mod core;
 mod cube;
+mod editor;
+mod font;
+mod gui;
+mod guides;
+mod headless;
+mod history;
+mod hud;
+mod inspector;
+mod offscreen;
+mod path;
+mod picking;
+mod recorder;
+mod rule;
 mod scene;
+mod settings;
+mod shadow;
 mod texture;
+mod transparency;
+mod volume;
 mod window;
 
 #[derive(Parser, Debug)]
@@ -22,10 +55,182 @@ struct Cli {
     /// Width of simulation grid, defaults to 30
     #[clap(short, long)]
     grid_width: Option<u32>,
+
+    /// How cells are coloured, press C to cycle while running
+    #[clap(short, long, arg_enum, default_value_t = ColorMode::Position)]
+    color_mode: ColorMode,
+
+    /// Gradient for the colour mode as comma separated hex colours, e.g. "#000000,#ff8800"
+    #[clap(short, long)]
+    palette: Option<Palette>,
+
+    /// Generations simulated per second, change with +/- while running, defaults to 5
+    #[clap(long)]
+    gps: Option<f32>,
+
+    /// File camera keyframes are loaded from and recorded to, defaults to camera_path.txt
+    #[clap(long)]
+    camera_path: Option<PathBuf>,
+
+    /// Start slowly orbiting the grid, toggle with T while running
+    #[clap(long)]
+    turntable: bool,
+
+    /// Turntable speed in degrees per second, defaults to 10
+    #[clap(long)]
+    turntable_speed: Option<f32>,
+
+    /// Turntable height above the horizon in degrees, defaults to 20
+    #[clap(long)]
+    turntable_elevation: Option<f32>,
+
+    /// Start with an orthographic rather than perspective projection, toggle with O while running
+    #[clap(short, long)]
+    orthographic: bool,
+
+    /// Render frames to PNGs without a window instead, e.g. "out/%05d.png"
+    #[clap(long)]
+    render_frames: Option<String>,
+
+    /// Number of generations to render after the first with --render-frames, defaults to 100
+    #[clap(long)]
+    steps: Option<u32>,
+
+    /// Size of the frames rendered with --render-frames, defaults to 1920x1080
+    #[clap(long)]
+    resolution: Option<Resolution>,
+
+    /// File recordings are saved to, .gif or .png for an APNG, defaults to recording.gif
+    #[clap(long)]
+    record: Option<PathBuf>,
+
+    /// Generations to record, e.g. "0..100", otherwise press R to start and stop recording
+    #[clap(long)]
+    record_range: Option<GenerationRange>,
+
+    /// Multiple of the window size screenshots taken with F12 are rendered at, defaults to 1
+    #[clap(long)]
+    screenshot_scale: Option<u32>,
+
+    /// Neighbour counts for birth and survival, e.g. "B4/S5,6" or "B4/S5-7", defaults to B4/S5,6
+    #[clap(short, long)]
+    rule: Option<Rule>,
+
+    /// What's beyond the edges of the grid, change in the settings panel while running
+    #[clap(short, long, arg_enum, default_value_t = Boundary::Dead)]
+    boundary: Boundary,
+
+    /// Fraction of cells alive in the first generation, defaults to 0.1
+    #[clap(short, long)]
+    density: Option<f32>,
+
+    /// Seed for the first generation, defaults to a random one
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// How cells are drawn, volume ray marches the grid instead of building a mesh
+    #[clap(long, arg_enum, default_value_t = RenderMode::Mesh)]
+    render_mode: RenderMode,
+
+    /// Opacity of a cell in the volume render mode, per cell the ray passes through, defaults to 0.5
+    #[clap(long)]
+    volume_density: Option<f32>,
+
+    /// Brightness of cells in the volume render mode, defaults to 1
+    #[clap(long)]
+    volume_emission: Option<f32>,
+
+    /// Opacity of cells in the transparent render mode as "START,END", from
+    /// newborn to old, defaults to 0.5,0.1
+    #[clap(long)]
+    opacity: Option<Opacity>,
+
+    /// Cells between lines on the grid's floor, defaults to no floor lines
+    #[clap(long)]
+    floor_grid: Option<u32>,
+
+    /// Start with the bounding box, floor lines and axis gizmo hidden, press V to show them
+    #[clap(long)]
+    hide_guides: bool,
+
+    /// Direction towards the light casting shadows in the mesh render mode as
+    /// "X,Y,Z", defaults to 0.5,1,0.3
+    #[clap(long)]
+    light_direction: Option<LightDirection>,
+
+    /// Strength of the glow around newborn and bright cells, 0 turns it off, defaults to 1
+    #[clap(long)]
+    bloom_intensity: Option<f32>,
+
+    /// Brightness above which cells glow as well as newborn ones, defaults to 1
+    #[clap(long)]
+    bloom_threshold: Option<f32>,
+
+    /// Tonemap the scene with the ACES curve instead of clipping bright colours
+    #[clap(long)]
+    tonemap: bool,
+
+    /// How edges are smoothed
+    #[clap(long, arg_enum, default_value_t = AntiAliasing::Smaa)]
+    aa: AntiAliasing,
 }
 
 fn main() {
     let cli = Cli::parse();
-    let grid_width = cli.grid_width.unwrap_or(30);
-    run(cli.fullscreen, cli.fps, grid_width);
+    let settings = Settings {
+        grid_width: cli.grid_width.unwrap_or(30),
+        color_scheme: ColorScheme::new(
+            cli.color_mode,
+            cli.palette,
+            cli.opacity.unwrap_or(Opacity {
+                start: 0.5,
+                end: 0.1,
+            }),
+        ),
+        gps: cli.gps.unwrap_or(5.),
+        camera_path: cli
+            .camera_path
+            .unwrap_or_else(|| PathBuf::from("camera_path.txt")),
+        turntable: Turntable {
+            enabled: cli.turntable,
+            speed: cli.turntable_speed.unwrap_or(10.).to_radians(),
+            elevation: cli.turntable_elevation.unwrap_or(20.).to_radians(),
+        },
+        orthographic: cli.orthographic,
+        recording: cli.record.unwrap_or_else(|| PathBuf::from("recording.gif")),
+        record_range: cli.record_range,
+        screenshot_scale: cli.screenshot_scale.unwrap_or(1),
+        rule: cli.rule.unwrap_or_default(),
+        boundary: cli.boundary,
+        density: cli.density.unwrap_or(0.1),
+        seed: cli.seed.unwrap_or_else(|| WyRand::new().generate()),
+        render_mode: cli.render_mode,
+        transfer: Transfer {
+            density: cli.volume_density.unwrap_or(0.5),
+            emission: cli.volume_emission.unwrap_or(1.),
+        },
+        floor_grid: cli.floor_grid.filter(|&spacing| spacing > 0),
+        guides: !cli.hide_guides,
+        light_direction: cli.light_direction.unwrap_or_default(),
+        bloom: BloomSettings {
+            threshold: cli.bloom_threshold.unwrap_or(1.),
+            intensity: cli.bloom_intensity.unwrap_or(1.),
+            tonemap: cli.tonemap,
+        },
+        anti_aliasing: cli.aa,
+    };
+
+    let result = if let Some(pattern) = cli.render_frames {
+        let resolution = cli.resolution.unwrap_or(Resolution {
+            width: 1920,
+            height: 1080,
+        });
+        headless::render_frames(&pattern, cli.steps.unwrap_or(100), resolution, settings)
+    } else {
+        run(cli.fullscreen, cli.fps, settings)
+    };
+    if let Err(e) = result {
+        eprintln!("{:?}", e);
+        std::process::exit(1);
+    }
 }
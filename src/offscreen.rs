@@ -0,0 +1,160 @@
+use std::{num::NonZeroU32, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use image::RgbaImage;
+use smaa::SmaaTarget;
+
+use crate::{settings::AntiAliasing, texture::Texture};
+
+/// Image size in pixels, parsed from "WIDTHxHEIGHT".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once(['x', 'X'])
+            .ok_or_else(|| anyhow!("{} should look like 1920x1080", s))?;
+        let resolution = Self {
+            width: width.trim().parse().context("Invalid width")?,
+            height: height.trim().parse().context("Invalid height")?,
+        };
+        if resolution.width == 0 || resolution.height == 0 {
+            return Err(anyhow!("Resolution can't be zero"));
+        }
+        Ok(resolution)
+    }
+}
+
+/// Render targets for frames that aren't presented to a window, plus a
+/// buffer the finished frame gets copied into so it can be read back.
+pub struct Offscreen {
+    pub resolution: Resolution,
+    format: wgpu::TextureFormat,
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub depth_texture: Texture,
+    pub smaa_target: SmaaTarget,
+    buffer: wgpu::Buffer,
+    // Rows in the buffer are padded out to the alignment copies need
+    padded_bytes_per_row: u32,
+}
+
+impl Offscreen {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        resolution: Resolution,
+        anti_aliasing: AntiAliasing,
+    ) -> Self {
+        let Resolution { width, height } = resolution;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_texture = Texture::create_depth_texture(
+            device,
+            width,
+            height,
+            anti_aliasing.sample_count(),
+            "offscreen_depth_texture",
+        );
+        let smaa_target = SmaaTarget::new(
+            device,
+            queue,
+            width,
+            height,
+            format,
+            anti_aliasing.smaa_mode(),
+        );
+
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            resolution,
+            format,
+            texture,
+            view,
+            depth_texture,
+            smaa_target,
+            buffer,
+            padded_bytes_per_row,
+        }
+    }
+
+    pub fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: self.resolution.width,
+                height: self.resolution.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Reads back whatever was last copied into the buffer as an RGBA image.
+    pub async fn read(&self, device: &wgpu::Device) -> anyhow::Result<RgbaImage> {
+        let Resolution { width, height } = self.resolution;
+        let slice = self.buffer.slice(..);
+        let future = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        future.await.context("Couldn't read back the frame")?;
+
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in data.chunks(self.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..(width * 4) as usize]);
+        }
+        drop(data);
+        self.buffer.unmap();
+
+        // Surfaces usually prefer BGRA, images are RGBA
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            pixels
+                .chunks_exact_mut(4)
+                .for_each(|pixel| pixel.swap(0, 2));
+        }
+
+        RgbaImage::from_raw(width, height, pixels).context("Frame was the wrong size")
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::camera::View;
+
+#[derive(Clone, Copy, Debug)]
+struct Keyframe {
+    generation: f32,
+    view: View,
+}
+
+impl Keyframe {
+    // eye x/y/z, target x/y/z, fovy
+    fn to_array(self) -> [f32; 7] {
+        let View { eye, target, fovy } = self.view;
+        [eye.x, eye.y, eye.z, target.x, target.y, target.z, fovy]
+    }
+
+    fn view_from_array(a: [f32; 7]) -> View {
+        View {
+            eye: (a[0], a[1], a[2]).into(),
+            target: (a[3], a[4], a[5]).into(),
+            fovy: a[6],
+        }
+    }
+}
+
+/// Camera keyframes at chosen generations, played back along a Catmull-Rom
+/// spline. Stored as a text file with one keyframe per line.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    file: PathBuf,
+}
+
+impl CameraPath {
+    /// Loads keyframes from `file` if it exists, it's also where recorded keyframes get saved.
+    pub fn load(file: PathBuf) -> Self {
+        let mut path = Self {
+            keyframes: Vec::new(),
+            file,
+        };
+        if path.file.exists() {
+            match path.read() {
+                Ok(keyframes) => path.keyframes = keyframes,
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+        path
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<Keyframe>> {
+        let text = fs::read_to_string(&self.file)
+            .with_context(|| format!("Couldn't read camera path {}", self.file.display()))?;
+
+        let mut keyframes = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<f32>, _>>()
+                .ok()
+                .filter(|values| values.len() == 8)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "{}:{} should be 8 numbers: generation eye target fovy",
+                        self.file.display(),
+                        number + 1
+                    )
+                })?;
+            keyframes.push(Keyframe {
+                generation: values[0],
+                view: Keyframe::view_from_array(values[1..].try_into().unwrap()),
+            });
+        }
+        keyframes.sort_by(|a, b| a.generation.total_cmp(&b.generation));
+        keyframes.dedup_by(|a, b| a.generation == b.generation);
+        Ok(keyframes)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let mut text =
+            String::from("# generation eye_x eye_y eye_z target_x target_y target_z fovy\n");
+        for keyframe in self.keyframes.iter() {
+            let values: Vec<String> = keyframe.to_array().iter().map(f32::to_string).collect();
+            text += &format!("{} {}\n", keyframe.generation, values.join(" "));
+        }
+        fs::write(&self.file, text)
+            .with_context(|| format!("Couldn't save camera path {}", self.file.display()))
+    }
+
+    /// Adds a keyframe, replacing any already at this generation, and saves the path.
+    pub fn record(&mut self, generation: usize, view: View) {
+        let keyframe = Keyframe {
+            generation: generation as f32,
+            view,
+        };
+        let i = self
+            .keyframes
+            .partition_point(|k| k.generation < keyframe.generation);
+        match self.keyframes.get_mut(i) {
+            Some(existing) if existing.generation == keyframe.generation => *existing = keyframe,
+            _ => self.keyframes.insert(i, keyframe),
+        }
+
+        match self.save() {
+            Ok(()) => println!(
+                "Recorded camera keyframe {} at generation {}",
+                self.keyframes.len(),
+                generation
+            ),
+            Err(e) => eprintln!("{:?}", e),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// View along the path at a (fractional) generation, holding still before
+    /// the first and after the last keyframe.
+    pub fn sample(&self, generation: f32) -> Option<View> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if generation <= first.generation {
+            return Some(first.view);
+        }
+        if generation >= last.generation {
+            return Some(last.view);
+        }
+
+        // Segment from keyframe i to i + 1 containing the generation
+        let i = self
+            .keyframes
+            .partition_point(|k| k.generation <= generation)
+            - 1;
+        let k1 = self.keyframes[i];
+        let k2 = self.keyframes[i + 1];
+        // Mirror the neighbouring keyframe where the path runs out
+        let mirror = |k: Keyframe, about: Keyframe| Keyframe {
+            generation: 2. * about.generation - k.generation,
+            view: Keyframe::view_from_array(add(
+                scale(about.to_array(), 2.),
+                scale(k.to_array(), -1.),
+            )),
+        };
+        let k0 = if i > 0 {
+            self.keyframes[i - 1]
+        } else {
+            mirror(k2, k1)
+        };
+        let k3 = match self.keyframes.get(i + 2) {
+            Some(&k) => k,
+            None => mirror(k1, k2),
+        };
+
+        Some(Keyframe::view_from_array(catmull_rom(
+            [k0, k1, k2, k3],
+            generation,
+        )))
+    }
+}
+
+/// Barry-Goldman evaluation of a Catmull-Rom spline, using the keyframe
+/// generations as knots so uneven spacing still moves smoothly.
+fn catmull_rom(k: [Keyframe; 4], t: f32) -> [f32; 7] {
+    let knots = k.map(|k| k.generation);
+    let points = k.map(|k| k.to_array());
+    let lerp = |a: [f32; 7], b: [f32; 7], ta: f32, tb: f32| {
+        let w = (t - ta) / (tb - ta);
+        add(scale(a, 1. - w), scale(b, w))
+    };
+
+    let a1 = lerp(points[0], points[1], knots[0], knots[1]);
+    let a2 = lerp(points[1], points[2], knots[1], knots[2]);
+    let a3 = lerp(points[2], points[3], knots[2], knots[3]);
+    let b1 = lerp(a1, a2, knots[0], knots[2]);
+    let b2 = lerp(a2, a3, knots[1], knots[3]);
+    lerp(b1, b2, knots[1], knots[2])
+}
+
+fn add(a: [f32; 7], b: [f32; 7]) -> [f32; 7] {
+    let mut sum = a;
+    sum.iter_mut().zip(b).for_each(|(x, y)| *x += y);
+    sum
+}
+
+fn scale(a: [f32; 7], s: f32) -> [f32; 7] {
+    a.map(|x| x * s)
+}
@@ -0,0 +1,208 @@
+use cgmath::{EuclideanSpace, InnerSpace, SquareMatrix};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// A ray in grid coordinates, one unit per cell.
+pub struct Ray {
+    pub origin: cgmath::Point3<f32>,
+    pub direction: cgmath::Vector3<f32>,
+}
+
+impl Ray {
+    /// Unprojects the cursor, in pixels from the top left of the window, from
+    /// the near plane to the far plane. Works for either projection.
+    pub fn from_cursor(
+        view_proj: cgmath::Matrix4<f32>,
+        cursor: PhysicalPosition<f64>,
+        size: PhysicalSize<u32>,
+    ) -> Option<Self> {
+        let inverse = view_proj.invert()?;
+        let x = cursor.x as f32 / size.width as f32 * 2. - 1.;
+        let y = 1. - cursor.y as f32 / size.height as f32 * 2.;
+        // wgpu clip space depth goes from 0 at the near plane to 1 at the far plane
+        let near = cgmath::Point3::from_homogeneous(inverse * cgmath::Vector4::new(x, y, 0., 1.));
+        let far = cgmath::Point3::from_homogeneous(inverse * cgmath::Vector4::new(x, y, 1., 1.));
+        Some(Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        })
+    }
+}
+
+/// The first cell a ray hit and the face it came in through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit {
+    pub cell: [i32; 3],
+    /// Points back out of the face, all zero if the ray started inside the cell
+    pub normal: [i32; 3],
+}
+
+impl Hit {
+    /// The cell in front of the face that was hit.
+    pub fn adjacent(&self) -> [i32; 3] {
+        [0, 1, 2].map(|k| self.cell[k] + self.normal[k])
+    }
+
+    /// The layer just below the grid, which only exists to place cells on.
+    pub fn is_floor(&self) -> bool {
+        self.cell[1] < 0
+    }
+}
+
+/// Steps along the ray a cell at a time with a 3D DDA (Amanatides & Woo)
+/// until it reaches a cell `is_solid` accepts. There's a floor under the
+/// grid so a ray through an empty grid still has somewhere to land.
+pub fn cast(ray: &Ray, grid_width: u32, is_solid: impl Fn([i32; 3]) -> bool) -> Option<Hit> {
+    let width = grid_width as i32;
+    let min = [0, -1, 0];
+    let max = [width, width, width];
+
+    // Clip the ray to the box around the grid and floor
+    let origin: [f32; 3] = ray.origin.into();
+    let direction: [f32; 3] = ray.direction.into();
+    let mut t_enter = 0f32;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = [0; 3];
+    for k in 0..3 {
+        if direction[k] == 0. {
+            if origin[k] < min[k] as f32 || origin[k] > max[k] as f32 {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (min[k] as f32 - origin[k]) / direction[k];
+        let t1 = (max[k] as f32 - origin[k]) / direction[k];
+        let (near, far) = (t0.min(t1), t0.max(t1));
+        if near > t_enter {
+            t_enter = near;
+            normal = [0; 3];
+            normal[k] = -direction[k].signum() as i32;
+        }
+        t_exit = t_exit.min(far);
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+
+    let start = ray.origin.to_vec() + ray.direction * t_enter;
+    let start: [f32; 3] = start.into();
+    let mut cell = [0; 3];
+    let mut step = [0; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for k in 0..3 {
+        // Rounding can leave the start just outside the box
+        cell[k] = (start[k].floor() as i32).clamp(min[k], max[k] - 1);
+        if direction[k] != 0. {
+            step[k] = direction[k].signum() as i32;
+            let boundary = if step[k] > 0 { cell[k] + 1 } else { cell[k] };
+            t_max[k] = (boundary as f32 - origin[k]) / direction[k];
+            t_delta[k] = direction[k].abs().recip();
+        }
+    }
+
+    loop {
+        if cell[1] < 0 || is_solid(cell) {
+            return Some(Hit { cell, normal });
+        }
+        let k = (0..3)
+            .min_by(|&a, &b| t_max[a].total_cmp(&t_max[b]))
+            .unwrap();
+        if t_max[k] > t_exit {
+            return None;
+        }
+        cell[k] += step[k];
+        if cell[k] < min[k] || cell[k] >= max[k] {
+            return None;
+        }
+        normal = [0; 3];
+        normal[k] = -step[k];
+        t_max[k] += t_delta[k];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ray(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+        Ray {
+            origin: origin.into(),
+            direction: cgmath::Vector3::from(direction).normalize(),
+        }
+    }
+
+    #[test]
+    fn axis_parallel_ray_lands_on_the_floor_of_an_empty_grid() {
+        let hit = cast(&ray([2.5, 50., 2.5], [0., -1., 0.]), 10, |_| false).unwrap();
+        assert_eq!(hit.cell, [2, -1, 2]);
+        assert_eq!(hit.normal, [0, 1, 0]);
+        assert!(hit.is_floor());
+        assert_eq!(hit.adjacent(), [2, 0, 2]);
+    }
+
+    #[test]
+    fn enters_through_the_face_it_crosses() {
+        let hit = cast(&ray([-5., 3.5, 4.5], [1., 0., 0.]), 10, |cell| {
+            cell == [0, 3, 4]
+        })
+        .unwrap();
+        assert_eq!(hit.cell, [0, 3, 4]);
+        assert_eq!(hit.normal, [-1, 0, 0]);
+        assert_eq!(hit.adjacent(), [-1, 3, 4]);
+    }
+
+    #[test]
+    fn starts_inside_the_grid() {
+        let hit = cast(&ray([1.5, 3.5, 3.5], [0., 0., 1.]), 10, |cell| cell[2] == 7).unwrap();
+        assert_eq!(hit.cell, [1, 3, 7]);
+        assert_eq!(hit.normal, [0, 0, -1]);
+
+        let inside = cast(&ray([1.5, 3.5, 3.5], [0., 0., 1.]), 10, |_| true).unwrap();
+        assert_eq!(inside.cell, [1, 3, 3]);
+        assert_eq!(inside.normal, [0, 0, 0]);
+    }
+
+    #[test]
+    fn diagonal_ray_steps_through_every_cell_it_crosses() {
+        let visited = std::cell::RefCell::new(Vec::new());
+        let hit = cast(&ray([0.5, 0.2, 0.5], [1., 0.3, 0.]), 4, |cell| {
+            visited.borrow_mut().push(cell);
+            cell == [3, 1, 0]
+        });
+        // y only reaches 1 past x = 3, so the last step is up through the bottom face
+        assert_eq!(hit.map(|hit| hit.normal), Some([0, -1, 0]));
+        assert_eq!(
+            visited.into_inner(),
+            [[0, 0, 0], [1, 0, 0], [2, 0, 0], [3, 0, 0], [3, 1, 0]]
+        );
+    }
+
+    #[test]
+    fn misses_the_grid() {
+        // Parallel to it but off to the side
+        assert_eq!(cast(&ray([-5., 20., 2.], [1., 0., 0.]), 10, |_| true), None);
+        // Pointing away from it
+        assert_eq!(
+            cast(&ray([-5., 5., 5.], [-1., 0.2, 0.]), 10, |_| true),
+            None
+        );
+        // Leaving through the top of an empty grid
+        assert_eq!(
+            cast(&ray([5.5, 5.5, 5.5], [0., 1., 0.]), 10, |_| false),
+            None
+        );
+    }
+
+    #[test]
+    fn cursor_in_the_middle_looks_straight_ahead() {
+        let size = PhysicalSize::new(200, 100);
+        let ray = Ray::from_cursor(
+            cgmath::Matrix4::identity(),
+            PhysicalPosition::new(100., 50.),
+            size,
+        )
+        .unwrap();
+        assert!((ray.origin - cgmath::Point3::new(0., 0., 0.)).magnitude() < 1e-6);
+        assert!((ray.direction - cgmath::Vector3::unit_z()).magnitude() < 1e-6);
+    }
+}
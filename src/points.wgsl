@@ -0,0 +1,100 @@
+// Draws each live cell as a sphere impostor, a camera facing square shaded
+// like a ball. color.wgsl is included ahead of this.
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>;
+    right: vec4<f32>;
+    up: vec4<f32>;
+};
+
+struct Transition {
+    // How far through the interval to the next generation, from 0 to 1
+    progress: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> camera: CameraUniform;
+
+// Same bind group as the cube pipeline
+[[group(1), binding(0)]]
+var<uniform> colors: ColorScheme;
+[[group(1), binding(1)]]
+var<storage, read> cells: Cells;
+[[group(1), binding(2)]]
+var<storage, read> previous_cells: Cells;
+[[group(1), binding(3)]]
+var<uniform> transition: Transition;
+
+// Half the width of a cell, so neighbouring spheres just touch
+let RADIUS: f32 = 0.5;
+
+// Brightness of the side facing away from the light
+let SHADOW_FLOOR: f32 = 0.35;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec3<f32>;
+    // Across the square from -1 to 1
+    [[location(1)]] uv: vec2<f32>;
+    [[location(2)]] glow: f32;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[builtin(instance_index)]] instance_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    var cell = cells.cells[instance_index];
+    let previous = previous_cells.cells[instance_index];
+
+    // Newborn cells grow into place and dying cells shrink away
+    let t = smoothStep(0.0, 1.0, transition.progress);
+    var scale = 1.0;
+    if (cell.state == DEAD_STATE) {
+        cell = previous;
+        scale = 1.0 - t;
+    } else if (previous.state == DEAD_STATE) {
+        scale = t;
+    }
+    if (cell.state == DEAD_STATE) {
+        // Outside the clip volume, so nothing is drawn
+        out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+        return out;
+    }
+
+    // Two triangles from six vertices
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let centre = vec3<f32>(f32(cell.x), f32(cell.y), f32(cell.z)) + 0.5;
+    let offset = (camera.right.xyz * corner.x + camera.up.xyz * corner.y) * RADIUS * scale;
+
+    out.clip_position = camera.view_proj * vec4<f32>(centre + offset, 1.0);
+    out.color = cell_color(colors, cell);
+    out.uv = corner;
+    out.glow = cell_glow(cell);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> SceneOutput {
+    let distance_squared = dot(in.uv, in.uv);
+    if (distance_squared > 1.0) {
+        discard;
+    }
+    // Normal of the sphere facing the camera, lit from the upper left
+    let normal = vec3<f32>(in.uv, sqrt(1.0 - distance_squared));
+    let light = normalize(vec3<f32>(-0.4, 0.6, 1.0));
+    let brightness = mix(SHADOW_FLOOR, 1.0, max(dot(normal, light), 0.0));
+    var out: SceneOutput;
+    out.color = vec4<f32>(in.color * brightness, 1.0);
+    out.emissive = vec4<f32>(in.color * in.glow, 1.0);
+    return out;
+}
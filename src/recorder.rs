@@ -0,0 +1,221 @@
+use std::{
+    borrow::Cow, collections::HashMap, fs::File, io::BufWriter, ops::Range, path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context};
+use color_quant::NeuQuant;
+use image::RgbaImage;
+
+/// Pixels sampled across the whole animation to build the GIF palette
+const PALETTE_SAMPLES: usize = 1 << 19;
+
+/// GIF palette index left for see-through pixels
+const TRANSPARENT_INDEX: u8 = 255;
+
+/// Generations to record, parsed from "START..END".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenerationRange(pub Range<usize>);
+
+impl FromStr for GenerationRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| anyhow!("{} should look like 0..100", s))?;
+        let start = start.trim().parse().context("Invalid first generation")?;
+        let end = end.trim().parse().context("Invalid last generation")?;
+        if start >= end {
+            return Err(anyhow!("{} doesn't contain any generations", s));
+        }
+        Ok(Self(start..end))
+    }
+}
+
+/// Captures a frame per generation and saves them as an animated GIF or
+/// APNG, depending on the file extension, once the range is over.
+pub struct Recorder {
+    file: PathBuf,
+    // None while idle, the end is left open when recording by hand
+    range: Option<Range<usize>>,
+    frames: Vec<RgbaImage>,
+    last_generation: Option<usize>,
+}
+
+impl Recorder {
+    pub fn new(file: PathBuf, range: Option<GenerationRange>) -> Self {
+        Self {
+            file,
+            range: range.map(|range| range.0),
+            frames: Vec::new(),
+            last_generation: None,
+        }
+    }
+
+    /// Starts recording from this generation on, or saves what's been recorded so far.
+    pub fn toggle(&mut self, generation: usize, frame_delay: f32) {
+        if self.range.is_some() {
+            self.finish(frame_delay);
+        } else {
+            println!("Recording from generation {}", generation);
+            self.range = Some(generation..usize::MAX);
+        }
+    }
+
+    /// Whether this generation still needs capturing. Generations skipped
+    /// over or rewound past aren't recorded.
+    pub fn wants_frame(&self, generation: usize) -> bool {
+        self.range
+            .as_ref()
+            .is_some_and(|range| range.contains(&generation))
+            && self.last_generation.is_none_or(|last| generation > last)
+    }
+
+    /// Most generations that can be stepped at once from `generation`
+    /// without skipping over one that needs capturing.
+    pub fn max_steps(&self, generation: usize) -> Option<u32> {
+        let range = self.range.as_ref().filter(|range| generation < range.end)?;
+        let to_start = range.start.saturating_sub(generation).max(1);
+        Some(to_start.min(u32::MAX as usize) as u32)
+    }
+
+    pub fn add_frame(&mut self, generation: usize, frame: RgbaImage) {
+        self.frames.push(frame);
+        self.last_generation = Some(generation);
+    }
+
+    /// Size of the frames so far, the rest need to match it.
+    pub fn frame_size(&self) -> Option<(u32, u32)> {
+        self.frames.first().map(|frame| frame.dimensions())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.range.is_some()
+    }
+
+    pub fn is_finished(&self, generation: usize) -> bool {
+        self.range
+            .as_ref()
+            .is_some_and(|range| generation >= range.end)
+    }
+
+    /// Saves the frames with `frame_delay` seconds between them and goes back to idle.
+    pub fn finish(&mut self, frame_delay: f32) {
+        let frames = std::mem::take(&mut self.frames);
+        self.range = None;
+        self.last_generation = None;
+        if frames.is_empty() {
+            println!("Stopped recording, nothing was recorded");
+            return;
+        }
+
+        match self.save(&frames, frame_delay) {
+            Ok(()) => println!("Saved {} frames to {}", frames.len(), self.file.display()),
+            Err(e) => eprintln!("{:?}", e),
+        }
+    }
+
+    fn save(&self, frames: &[RgbaImage], frame_delay: f32) -> anyhow::Result<()> {
+        let file = File::create(&self.file)
+            .with_context(|| format!("Couldn't create {}", self.file.display()))?;
+        let writer = BufWriter::new(file);
+        let extension = self
+            .file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("gif") => save_gif(writer, frames, frame_delay),
+            Some("png" | "apng") => save_apng(writer, frames, frame_delay),
+            _ => Err(anyhow!(
+                "{} should end in .gif or .png",
+                self.file.display()
+            )),
+        }
+        .with_context(|| format!("Couldn't save recording {}", self.file.display()))
+    }
+}
+
+/// Quantises every frame with one palette built from the whole animation,
+/// so gradients don't shift and band from frame to frame.
+fn save_gif(writer: BufWriter<File>, frames: &[RgbaImage], frame_delay: f32) -> anyhow::Result<()> {
+    let (width, height) = frames[0].dimensions();
+    let (width, height) = (
+        u16::try_from(width).context("Too wide for a GIF")?,
+        u16::try_from(height).context("Too tall for a GIF")?,
+    );
+
+    // Only opaque pixels get colours, the last slot is for transparency
+    let opaque_count: usize = frames
+        .iter()
+        .map(|frame| frame.pixels().filter(|pixel| pixel[3] >= 128).count())
+        .sum();
+    let stride = (opaque_count / PALETTE_SAMPLES).max(1);
+    let mut samples: Vec<u8> = frames
+        .iter()
+        .flat_map(|frame| frame.pixels())
+        .filter(|pixel| pixel[3] >= 128)
+        .step_by(stride)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    if samples.is_empty() {
+        samples.extend([0, 0, 0, 255]);
+    }
+    let quantizer = NeuQuant::new(10, TRANSPARENT_INDEX as usize, &samples);
+    let mut palette = quantizer.color_map_rgb();
+    palette.resize(256 * 3, 0);
+
+    let mut encoder = gif::Encoder::new(writer, width, height, &palette)?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    // In hundredths of a second, viewers tend to slow down anything under 2
+    let delay = (frame_delay * 100.).round().clamp(2., u16::MAX as f32) as u16;
+    let mut indices = HashMap::new();
+    for frame in frames {
+        let buffer: Vec<u8> = frame
+            .pixels()
+            .map(|pixel| {
+                if pixel[3] < 128 {
+                    return TRANSPARENT_INDEX;
+                }
+                let [r, g, b, _] = pixel.0;
+                *indices
+                    .entry([r, g, b])
+                    .or_insert_with(|| quantizer.index_of(&[r, g, b, 255]) as u8)
+            })
+            .collect();
+        encoder.write_frame(&gif::Frame {
+            width,
+            height,
+            delay,
+            // Clear each frame first so transparent areas don't show the last one
+            dispose: gif::DisposalMethod::Background,
+            transparent: Some(TRANSPARENT_INDEX),
+            buffer: Cow::Owned(buffer),
+            ..Default::default()
+        })?;
+    }
+    Ok(())
+}
+
+fn save_apng(
+    writer: BufWriter<File>,
+    frames: &[RgbaImage],
+    frame_delay: f32,
+) -> anyhow::Result<()> {
+    let (width, height) = frames[0].dimensions();
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    // In milliseconds
+    let delay = (frame_delay * 1000.).round().clamp(1., u16::MAX as f32) as u16;
+    encoder.set_frame_delay(delay, 1000)?;
+
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(frame.as_raw())?;
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Context};
+
+/// Neighbour counts from 0 to 26 in a 3x3x3 Moore neighbourhood
+pub const NEIGHBOUR_COUNTS: usize = 27;
+
+// Following Death/Survive/Birth -> 0/1/2, must match compute.wgsl
+const SURVIVE_RULE: u32 = 1;
+const BIRTH_RULE: u32 = 2;
+
+/// What lies beyond the edges of the grid.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Cells outside the grid are always dead
+    Dead,
+    /// The grid wraps around, so opposite faces are neighbours
+    Wrap,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 2] = [Boundary::Dead, Boundary::Wrap];
+}
+
+/// Which live neighbour counts give birth to a dead cell and which keep a
+/// live cell alive, written like "B4/S5,6".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: [bool; NEIGHBOUR_COUNTS],
+    pub survive: [bool; NEIGHBOUR_COUNTS],
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        "B4/S5,6".parse().unwrap()
+    }
+}
+
+impl Rule {
+    /// One rule per neighbour count, survival taking over from birth where a
+    /// count is in both.
+    pub fn to_uniform(self, grid_width: u32, boundary: Boundary) -> RulesetUniform {
+        let mut ruleset = [0; NEIGHBOUR_COUNTS];
+        for (count, rule) in ruleset.iter_mut().enumerate() {
+            if self.survive[count] {
+                *rule = SURVIVE_RULE;
+            } else if self.birth[count] {
+                *rule = BIRTH_RULE;
+            }
+        }
+        RulesetUniform {
+            ruleset,
+            grid_width,
+            boundary: boundary as u32,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |flags: &[bool; NEIGHBOUR_COUNTS]| {
+            (0..NEIGHBOUR_COUNTS)
+                .filter(|&count| flags[count])
+                .map(|count| count.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survive))
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    /// Parses "B4/S5,6", counts can also be ranges like "S5-7".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rule = Self {
+            birth: [false; NEIGHBOUR_COUNTS],
+            survive: [false; NEIGHBOUR_COUNTS],
+        };
+        for part in s.split('/') {
+            let part = part.trim();
+            let (flags, counts) = match part.chars().next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => (&mut rule.birth, &part[1..]),
+                Some('S') => (&mut rule.survive, &part[1..]),
+                _ => bail!("{} should look like B4/S5,6", s),
+            };
+            for range in counts.split(',').filter(|range| !range.trim().is_empty()) {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let start: usize = start.trim().parse().context("Invalid neighbour count")?;
+                let end: usize = end.trim().parse().context("Invalid neighbour count")?;
+                if start > end || end >= NEIGHBOUR_COUNTS {
+                    return Err(anyhow!("{} isn't a range of counts from 0 to 26", range));
+                }
+                flags[start..=end].iter_mut().for_each(|flag| *flag = true);
+            }
+        }
+        Ok(rule)
+    }
+}
+
+// Layout must match `Ruleset` in compute.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RulesetUniform {
+    ruleset: [u32; NEIGHBOUR_COUNTS],
+    grid_width: u32,
+    boundary: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(flags: &[bool; NEIGHBOUR_COUNTS]) -> Vec<usize> {
+        (0..NEIGHBOUR_COUNTS).filter(|&n| flags[n]).collect()
+    }
+
+    #[test]
+    fn parses_lists_and_ranges() {
+        let rule: Rule = "B4,6/S5-7,10".parse().unwrap();
+        assert_eq!(counts(&rule.birth), [4, 6]);
+        assert_eq!(counts(&rule.survive), [5, 6, 7, 10]);
+    }
+
+    #[test]
+    fn parses_lowercase_and_empty_parts() {
+        let rule: Rule = " b4 / s ".parse().unwrap();
+        assert_eq!(counts(&rule.birth), [4]);
+        assert!(counts(&rule.survive).is_empty());
+        assert_eq!("B4/S5,,6".parse::<Rule>().unwrap(), Rule::default());
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let rule: Rule = "B0,26/S1-3".parse().unwrap();
+        assert_eq!(rule.to_string(), "B0,26/S1,2,3");
+        assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        for s in [
+            "", "4/5,6", "B4/X5", "B4/S27", "B4/S6-5", "B4/Sx", "B4/S5-", "B-1",
+        ] {
+            assert!(s.parse::<Rule>().is_err(), "{:?} should be rejected", s);
+        }
+    }
+
+    #[test]
+    fn uniform_prefers_survival_for_counts_in_both_lists() {
+        let rule: Rule = "B3,4/S4,5".parse().unwrap();
+        let uniform = rule.to_uniform(30, Boundary::Wrap);
+        assert_eq!(uniform.ruleset[3], BIRTH_RULE);
+        assert_eq!(uniform.ruleset[4], SURVIVE_RULE);
+        assert_eq!(uniform.ruleset[5], SURVIVE_RULE);
+        assert_eq!(uniform.ruleset[6], 0);
+        assert_eq!(uniform.boundary, Boundary::Wrap as u32);
+    }
+}
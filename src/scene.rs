@@ -4,7 +4,9 @@ use crate::cube::Cube;
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
-    pub color: [f32; 3],
+    pub ao: f32,
+    /// Grid coordinates of the cell this vertex belongs to
+    pub cell: [u32; 3],
 }
 
 impl Vertex {
@@ -23,7 +25,12 @@ impl Vertex {
                 wgpu::VertexAttribute {
                     offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Uint32x3,
                 },
             ],
         }
@@ -43,28 +50,28 @@ impl Scene {
 
     pub fn _new_stairs() -> Self {
         let cubes = vec![
-            Cube::new(3., 3., 3., 1., [1., 0., 0.]),
-            Cube::new(3., 4., 3., 1., [0., 1., 0.]),
-            Cube::new(3., 3., 4., 1., [0., 0., 1.]),
-            Cube::new(4., 4., 4., 1., [1., 1., 0.5]),
+            Cube::new(3., 3., 3., 1.),
+            Cube::new(3., 4., 3., 1.),
+            Cube::new(3., 3., 4., 1.),
+            Cube::new(4., 4., 4., 1.),
         ];
         Self { cubes }
     }
 
     pub fn new_tube() -> Self {
         let cubes = vec![
-            Cube::new(5., 5., 5., 1., [1., 0., 0.]),
-            Cube::new(6., 5., 5., 1., [1., 0., 0.]),
-            Cube::new(7., 5., 5., 1., [1., 0., 0.]),
-            Cube::new(5., 7., 7., 1., [1., 0., 0.]),
-            Cube::new(6., 7., 7., 1., [1., 0., 0.]),
-            Cube::new(7., 7., 7., 1., [1., 0., 0.]),
-            Cube::new(5., 5., 7., 1., [1., 0., 0.]),
-            Cube::new(6., 5., 7., 1., [1., 0., 0.]),
-            Cube::new(7., 5., 7., 1., [1., 0., 0.]),
-            Cube::new(5., 7., 5., 1., [1., 0., 0.]),
-            Cube::new(6., 7., 5., 1., [1., 0., 0.]),
-            Cube::new(7., 7., 5., 1., [1., 0., 0.]),
+            Cube::new(5., 5., 5., 1.),
+            Cube::new(6., 5., 5., 1.),
+            Cube::new(7., 5., 5., 1.),
+            Cube::new(5., 7., 7., 1.),
+            Cube::new(6., 7., 7., 1.),
+            Cube::new(7., 7., 7., 1.),
+            Cube::new(5., 5., 7., 1.),
+            Cube::new(6., 5., 7., 1.),
+            Cube::new(7., 5., 7., 1.),
+            Cube::new(5., 7., 5., 1.),
+            Cube::new(6., 7., 5., 1.),
+            Cube::new(7., 7., 5., 1.),
         ];
         Self { cubes }
     }
@@ -74,8 +81,8 @@ impl Scene {
     }
 
     pub fn get_vertices_and_indices(&mut self) -> (Vec<Vertex>, Vec<u32>) {
-        let mut vertices: Vec<Vertex> = Vec::with_capacity((self.cubes.len() * 8) as usize);
-        let mut indices: Vec<u32> = Vec::with_capacity((self.cubes.len() * 36) as usize);
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(self.cubes.len() * 24);
+        let mut indices: Vec<u32> = Vec::with_capacity(self.cubes.len() * 36);
         let mut running_index = 0;
         for cube in self.cubes.iter() {
             vertices.extend(&cube.vertices);
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use crate::{
+    bloom::BloomSettings,
+    camera::Turntable,
+    color::ColorScheme,
+    recorder::GenerationRange,
+    rule::{Boundary, Rule},
+    shadow::LightDirection,
+    volume::Transfer,
+};
+
+/// How the cells are drawn.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// A cube per live cell
+    Mesh,
+    /// Ray marched glowing fog, for grids too big to build a mesh for
+    Volume,
+    /// A shaded sphere per live cell, drawn straight from the cell buffer
+    Points,
+    /// Semi-transparent cubes, so the structure inside shows through
+    Transparent,
+}
+
+/// How edges are smoothed.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AntiAliasing {
+    /// Jagged edges, for GPUs that struggle with the rest
+    None,
+    /// SMAA 1x post process
+    Smaa,
+    /// 4 samples per pixel, the only multisample count wgpu 0.12 allows
+    Msaa4,
+}
+
+impl AntiAliasing {
+    /// Samples per pixel in the scene's targets.
+    pub fn sample_count(self) -> u32 {
+        match self {
+            AntiAliasing::None | AntiAliasing::Smaa => 1,
+            AntiAliasing::Msaa4 => 4,
+        }
+    }
+
+    pub fn smaa_mode(self) -> smaa::SmaaMode {
+        match self {
+            AntiAliasing::Smaa => smaa::SmaaMode::Smaa1X,
+            _ => smaa::SmaaMode::Disabled,
+        }
+    }
+}
+
+/// Simulation options, resolved from the command line.
+pub struct Settings {
+    pub grid_width: u32,
+    pub color_scheme: ColorScheme,
+    pub gps: f32,
+    pub camera_path: PathBuf,
+    pub turntable: Turntable,
+    pub orthographic: bool,
+    pub recording: PathBuf,
+    pub record_range: Option<GenerationRange>,
+    pub screenshot_scale: u32,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub density: f32,
+    pub seed: u64,
+    pub render_mode: RenderMode,
+    pub transfer: Transfer,
+    pub floor_grid: Option<u32>,
+    pub guides: bool,
+    pub light_direction: LightDirection,
+    pub bloom: BloomSettings,
+    pub anti_aliasing: AntiAliasing,
+}
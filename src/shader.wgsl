@@ -2,34 +2,177 @@
 
 struct CameraUniform {
     view_proj: mat4x4<f32>;
+    right: vec4<f32>;
+    up: vec4<f32>;
 };
 
 [[group(0), binding(0)]] // 1.
 var<uniform> camera: CameraUniform;
 
+struct Transition {
+    // How far through the interval to the next generation, from 0 to 1
+    progress: f32;
+};
+
+// Cells under the cursor while editing
+struct Highlight {
+    x: i32;
+    y: i32;
+    z: i32;
+    radius: i32;
+    shape: u32;
+    active: u32;
+    padding: vec2<u32>;
+};
+
+[[group(1), binding(0)]]
+var<uniform> colors: ColorScheme;
+[[group(1), binding(1)]]
+var<storage, read> cells: Cells;
+[[group(1), binding(2)]]
+var<storage, read> previous_cells: Cells;
+[[group(1), binding(3)]]
+var<uniform> transition: Transition;
+[[group(1), binding(4)]]
+var<uniform> highlight: Highlight;
+
+// Directional light the shadow map is rendered from
+struct Light {
+    view_proj: mat4x4<f32>;
+    // Towards the light
+    direction: vec4<f32>;
+};
+
+[[group(2), binding(0)]]
+var<uniform> light: Light;
+[[group(2), binding(1)]]
+var shadow_map: texture_depth_2d;
+[[group(2), binding(2)]]
+var shadow_sampler: sampler_comparison;
+
+// Brightness of a fully occluded corner
+let AO_FLOOR: f32 = 0.35;
+
+// Brightness of faces the light doesn't reach
+let SHADOW_FLOOR: f32 = 0.55;
+
+// Order follows BrushShape in editor.rs
+let SPHERE_SHAPE: u32 = 0u;
+
 struct VertexInput {
     [[location(0)]] position: vec3<f32>;
-    [[location(1)]] color: vec3<f32>;
+    [[location(1)]] ao: f32;
+    [[location(2)]] cell: vec3<u32>;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec3<f32>;
+    // Only used by the transparent render mode
+    [[location(1)]] alpha: f32;
+    [[location(2)]] view_depth: f32;
+    [[location(3)]] world_position: vec3<f32>;
+    [[location(4)]] glow: f32;
 };
 
+fn is_highlighted(cell: vec3<u32>) -> bool {
+    if (highlight.active == 0u) {
+        return false;
+    }
+    let offset = vec3<i32>(cell) - vec3<i32>(highlight.x, highlight.y, highlight.z);
+    if (highlight.shape == SPHERE_SHAPE) {
+        // dot() only takes floats
+        let squared = offset * offset;
+        return squared.x + squared.y + squared.z <= highlight.radius * highlight.radius;
+    }
+    return max(max(abs(offset.x), abs(offset.y)), abs(offset.z)) <= highlight.radius;
+}
+
 [[stage(vertex)]]
 fn vs_main(
     model: VertexInput,
 ) -> VertexOutput {
+    let w = colors.grid_width;
+    let index = model.cell.z + model.cell.y * w + model.cell.x * w * w;
+    var cell = cells.cells[index];
+    let previous = previous_cells.cells[index];
+
+    // Newborn cells grow into place and dying cells shrink away
+    let t = smoothStep(0.0, 1.0, transition.progress);
+    var scale = 1.0;
+    if (cell.state == DEAD_STATE) {
+        cell = previous;
+        scale = 1.0 - t;
+    } else if (previous.state == DEAD_STATE) {
+        scale = t;
+    }
+    let centre = vec3<f32>(model.cell) + 0.5;
+    let position = centre + (model.position - centre) * scale;
+
     var out: VertexOutput;
-    out.color = model.color;
-    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
+    out.color = cell_color(colors, cell) * mix(AO_FLOOR, 1.0, model.ao);
+    if (is_highlighted(model.cell)) {
+        out.color = mix(out.color, vec3<f32>(1.0), 0.5);
+    }
+    out.alpha = cell_opacity(colors, cell);
+    out.glow = cell_glow(cell);
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.view_depth = out.clip_position.w;
+    out.world_position = position;
     return out;
 }
 
 // Fragment shader
 
+// Fraction of the light reaching a point, averaged over a 3x3 block of
+// shadow map texels to soften the edges
+fn shadow(world_position: vec3<f32>) -> f32 {
+    let position = light.view_proj * vec4<f32>(world_position, 1.0);
+    let uv = position.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+    var lit = 0.0;
+    for (var x: i32 = -1; x <= 1; x = x + 1) {
+        for (var y: i32 = -1; y <= 1; y = y + 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit = lit + textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, position.z);
+        }
+    }
+    return lit / 9.0;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> SceneOutput {
+    // Faces are flat, so the normal comes from how the position changes
+    // across the screen, y goes down the screen so this faces the camera
+    let normal = normalize(cross(dpdy(in.world_position), dpdx(in.world_position)));
+    var lit = shadow(in.world_position);
+    if (dot(normal, light.direction.xyz) <= 0.0) {
+        lit = 0.0;
+    }
+    var out: SceneOutput;
+    out.color = vec4<f32>(in.color * mix(SHADOW_FLOOR, 1.0, lit), 1.0);
+    out.emissive = vec4<f32>(in.color * in.glow, 1.0);
+    return out;
+}
+
+// Weighted blended order independent transparency, summed up by the
+// composite pass in transparency.wgsl
+
+struct AccumulateOutput {
+    // Premultiplied colour and alpha, both scaled by the weight
+    [[location(0)]] accum: vec4<f32>;
+    // Multiplied into how much of the background shows through
+    [[location(1)]] revealage: f32;
+};
+
 [[stage(fragment)]]
-fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
-}
\ No newline at end of file
+fn fs_accumulate(in: VertexOutput) -> AccumulateOutput {
+    // Nearer surfaces count for more, from McGuire and Bavoil's paper
+    let z = in.view_depth;
+    let weight = clamp(10.0 / (1e-5 + pow(z / 5.0, 2.0) + pow(z / 200.0, 6.0)), 1e-2, 3e3);
+
+    var out: AccumulateOutput;
+    out.accum = vec4<f32>(in.color * in.alpha, in.alpha) * in.alpha * weight;
+    out.revealage = in.alpha;
+    return out;
+}
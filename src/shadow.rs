@@ -0,0 +1,296 @@
+use std::{mem, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use cgmath::InnerSpace;
+
+use crate::{camera::OPENGL_TO_WGPU_MATRIX, scene::Vertex, texture::Texture};
+
+/// Width and height of the shadow map in texels
+const SHADOW_MAP_SIZE: u32 = 2048;
+
+/// Direction towards a light infinitely far away, written like "0.5,1,0.3".
+#[derive(Clone, Copy, Debug)]
+pub struct LightDirection(pub cgmath::Vector3<f32>);
+
+impl FromStr for LightDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let components = s
+            .split(',')
+            .map(|n| n.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid light direction")?;
+        let direction = match components[..] {
+            [x, y, z] => Some(cgmath::Vector3::new(x, y, z).normalize()),
+            _ => None,
+        };
+        // All zero, NaN and infinite or huge components don't come out a unit length
+        direction
+            .filter(|direction| (direction.magnitude() - 1.).abs() < 1e-3)
+            .map(Self)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} should be three finite numbers like 0.5,1,0.3, not all zero",
+                    s
+                )
+            })
+    }
+}
+
+impl Default for LightDirection {
+    /// From above, off to one side so the faces facing each way are shaded differently
+    fn default() -> Self {
+        Self(cgmath::Vector3::new(0.5, 1., 0.3).normalize())
+    }
+}
+
+// Layout must match `Light` in shader.wgsl. It's also bound in the camera's
+// place for the shadow pass, so it's padded out to the size of `CameraUniform`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightUniform {
+    view_proj: [[f32; 4]; 4],
+    direction: [f32; 4],
+    _padding: [f32; 4],
+}
+
+/// Depth of the live cubes as seen from a directional light, which the cube
+/// shader compares against to darken whatever the light can't reach.
+pub struct Shadow {
+    pipeline: wgpu::RenderPipeline,
+    map: Texture,
+    light_buffer: wgpu::Buffer,
+    /// Stands in for the camera while rendering the shadow map
+    light_bind_group: wgpu::BindGroup,
+    /// Group 2 of the cube pipeline, for sampling the shadow map
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    direction: cgmath::Vector3<f32>,
+}
+
+impl Shadow {
+    /// `shader` is the cube shader, whose vertex stage the shadow map is rendered with.
+    pub fn new(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        cell_render_bind_group_layout: &wgpu::BindGroupLayout,
+        direction: LightDirection,
+    ) -> Self {
+        let map = Texture::create_depth_texture(
+            device,
+            SHADOW_MAP_SIZE,
+            SHADOW_MAP_SIZE,
+            1,
+            "shadow_texture",
+        );
+
+        let light_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Buffer"),
+            size: mem::size_of::<LightUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let light_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("light_bind_group_layout"),
+            });
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &light_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+            label: Some("light_bind_group"),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+            label: Some("shadow_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: light_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&map.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&map.sampler),
+                },
+            ],
+            label: Some("shadow_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&light_bind_group_layout, cell_render_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            // Only depth is written
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // The back faces are stored, so lit faces don't shadow themselves
+                cull_mode: Some(wgpu::Face::Front),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            map,
+            light_buffer,
+            light_bind_group,
+            bind_group_layout,
+            bind_group,
+            direction: direction.0,
+        }
+    }
+
+    /// Fits the light's view around the whole grid.
+    pub fn write_uniform(&self, queue: &wgpu::Queue, grid_width: u32) {
+        let half_width = grid_width as f32 / 2.;
+        let centre = cgmath::Point3::new(half_width, half_width, half_width);
+        // Half the grid's diagonal, so every corner is in view whichever way the light points
+        let radius = half_width * 3f32.sqrt() + 1.;
+        let eye = centre + self.direction * radius * 2.;
+        let up = if self.direction.y.abs() > 0.99 {
+            cgmath::Vector3::unit_z()
+        } else {
+            cgmath::Vector3::unit_y()
+        };
+        let view = cgmath::Matrix4::look_at_rh(eye, centre, up);
+        let proj = cgmath::ortho(-radius, radius, -radius, radius, radius, radius * 3.);
+
+        let uniform = LightUniform {
+            view_proj: (OPENGL_TO_WGPU_MATRIX * proj * view).into(),
+            direction: self.direction.extend(0.).into(),
+            _padding: [0.; 4],
+        };
+        queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Starts the pass the mesh gets drawn into the shadow map in, with the
+    /// light bound in place of the camera.
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Render Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.map.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.light_bind_group, &[]);
+        render_pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_normalises_a_direction() {
+        let LightDirection(direction) = " 0, 2 ,0".parse().unwrap();
+        assert_eq!(direction, cgmath::Vector3::unit_y());
+        let LightDirection(direction) = "1,1,1".parse().unwrap();
+        assert!((direction.magnitude() - 1.).abs() < 1e-6);
+        assert!((direction.x - direction.z).abs() < 1e-6);
+    }
+
+    #[test]
+    fn default_points_up_and_is_normalised() {
+        let LightDirection(direction) = LightDirection::default();
+        assert!(direction.y > 0.);
+        assert!((direction.magnitude() - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rejects_malformed_directions() {
+        for s in [
+            "",
+            "0,0,0",
+            "1,1",
+            "1,1,1,1",
+            "1,,1",
+            "up",
+            "1,NaN,1",
+            "inf,0,0",
+            "1e30,1e30,1e30",
+        ] {
+            assert!(
+                s.parse::<LightDirection>().is_err(),
+                "{:?} should be rejected",
+                s
+            );
+        }
+    }
+}
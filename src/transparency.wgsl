@@ -0,0 +1,28 @@
+// Resolves the weighted sums from fs_accumulate in shader.wgsl into one
+// colour, blended over whatever is already drawn
+
+[[group(0), binding(0)]]
+var accum_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var revealage_texture: texture_2d<f32>;
+
+// Keeps the division finite where almost nothing was drawn
+let EPSILON: f32 = 1e-5;
+
+// One triangle covering the whole screen
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> [[builtin(position)]] vec4<f32> {
+    let position = vec2<f32>(f32(vertex_index & 1u) * 4.0 - 1.0, f32(vertex_index >> 1u) * 4.0 - 1.0);
+    return vec4<f32>(position, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let revealage = textureLoad(revealage_texture, pixel, 0).r;
+    if (revealage >= 1.0) {
+        discard;
+    }
+    let accum = textureLoad(accum_texture, pixel, 0);
+    return vec4<f32>(accum.rgb / max(accum.a, EPSILON), 1.0 - revealage);
+}
@@ -0,0 +1,195 @@
+use cgmath::SquareMatrix;
+
+use crate::{bloom, camera::Camera, texture::Texture};
+
+/// How cells turn into fog when ray marched.
+#[derive(Clone, Copy, Debug)]
+pub struct Transfer {
+    /// Opacity of a live cell per cell width the ray travels through it
+    pub density: f32,
+    /// Brightness of the light a cell gives off, times its colour
+    pub emission: f32,
+}
+
+// Layout must match `Volume` in volume.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct VolumeUniform {
+    inverse_view_proj: [[f32; 4]; 4],
+    grid_width: u32,
+    _padding: u32,
+    density: f32,
+    emission: f32,
+}
+
+/// Draws the grid as a glowing volume with a full screen ray march through
+/// the cell buffer, so there's no mesh to build however big the grid gets.
+pub struct Volume {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_groups: Vec<wgpu::BindGroup>,
+    transfer: Transfer,
+}
+
+impl Volume {
+    pub fn new(
+        device: &wgpu::Device,
+        cell_buffers: &[wgpu::Buffer],
+        color_buffer: &wgpu::Buffer,
+        transfer: Transfer,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Volume Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("color.wgsl"), include_str!("volume.wgsl")).into(),
+            ),
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Volume Buffer"),
+            size: std::mem::size_of::<VolumeUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("volume_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Volume Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Volume Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &bloom::scene_targets(
+                    wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+                    false,
+                ),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Shares the scene's render pass, but has no depth of its own
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        let mut volume = Self {
+            pipeline,
+            uniform_buffer,
+            bind_group_layout,
+            bind_groups: Vec::new(),
+            transfer,
+        };
+        volume.set_cell_buffers(device, cell_buffers, color_buffer);
+        volume
+    }
+
+    /// Points at new cell buffers, after the grid has been resized.
+    pub fn set_cell_buffers(
+        &mut self,
+        device: &wgpu::Device,
+        cell_buffers: &[wgpu::Buffer],
+        color_buffer: &wgpu::Buffer,
+    ) {
+        self.bind_groups = cell_buffers
+            .iter()
+            .map(|cell_buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.uniform_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: color_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: cell_buffer.as_entire_binding(),
+                        },
+                    ],
+                    label: Some("volume_bind_group"),
+                })
+            })
+            .collect();
+    }
+
+    /// Keeps the rays following the camera.
+    pub fn write_uniform(&self, queue: &wgpu::Queue, camera: &Camera, grid_width: u32) {
+        let inverse_view_proj = camera
+            .build_view_projection_matrix()
+            .invert()
+            .unwrap_or_else(cgmath::Matrix4::identity);
+        let uniform = VolumeUniform {
+            inverse_view_proj: inverse_view_proj.into(),
+            grid_width,
+            _padding: 0,
+            density: self.transfer.density,
+            emission: self.transfer.emission,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, generation: usize) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_groups[generation % 2], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
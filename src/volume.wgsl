@@ -0,0 +1,112 @@
+// Ray marches the grid a cell at a time, color.wgsl is included ahead of this
+
+struct Volume {
+    inverse_view_proj: mat4x4<f32>;
+    grid_width: u32;
+    padding: u32;
+    // Opacity of a live cell per unit the ray travels through it
+    density: f32;
+    // Brightness of the light given off by a cell
+    emission: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> volume: Volume;
+[[group(0), binding(1)]]
+var<uniform> colors: ColorScheme;
+[[group(0), binding(2)]]
+var<storage, read> cells: Cells;
+
+// Stop once nothing behind would show through
+let MIN_TRANSMITTANCE: f32 = 0.01;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] ndc: vec2<f32>;
+};
+
+// One triangle covering the whole screen
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32(vertex_index & 1u) * 4.0 - 1.0, f32(vertex_index >> 1u) * 4.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.ndc = position;
+    return out;
+}
+
+fn unproject(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let position = volume.inverse_view_proj * vec4<f32>(ndc, depth, 1.0);
+    return position.xyz / position.w;
+}
+
+// Transfer functions
+fn cell_density(cell: Cell) -> f32 {
+    return volume.density;
+}
+
+fn cell_emission(cell: Cell) -> vec3<f32> {
+    return cell_color(colors, cell) * volume.emission;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let origin = unproject(in.ndc, 0.0);
+    var direction = normalize(unproject(in.ndc, 1.0) - origin);
+    // Keeps the reciprocals finite
+    direction = select(direction, vec3<f32>(1e-6), abs(direction) < vec3<f32>(1e-6));
+    let inverse = 1.0 / direction;
+
+    // Clip the ray to the grid
+    let w = volume.grid_width;
+    let t0 = -origin * inverse;
+    let t1 = (vec3<f32>(f32(w)) - origin) * inverse;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    let t_enter = max(max(max(near.x, near.y), near.z), 0.0);
+    let t_exit = min(min(far.x, far.y), far.z);
+    if (t_enter >= t_exit) {
+        discard;
+    }
+
+    // 3D DDA through the cells, accumulating front to back
+    let start = origin + direction * t_enter;
+    var cell = clamp(vec3<i32>(floor(start)), vec3<i32>(0), vec3<i32>(i32(w) - 1));
+    let step = vec3<i32>(sign(direction));
+    let t_delta = abs(inverse);
+    var t_max = (vec3<f32>(cell) + max(vec3<f32>(step), vec3<f32>(0.0)) - origin) * inverse;
+    var t = t_enter;
+    var color = vec3<f32>(0.0);
+    var transmittance = 1.0;
+    loop {
+        if (t >= t_exit || transmittance < MIN_TRANSMITTANCE) {
+            break;
+        }
+        let t_next = min(min(t_max.x, t_max.y), t_max.z);
+        let index = vec3<u32>(cell);
+        let sample = cells.cells[index.z + index.y * w + index.x * w * w];
+        if (sample.state != DEAD_STATE) {
+            let alpha = 1.0 - exp(-cell_density(sample) * (min(t_next, t_exit) - t));
+            color = color + transmittance * alpha * cell_emission(sample);
+            transmittance = transmittance * (1.0 - alpha);
+        }
+
+        t = t_next;
+        if (t_max.x == t_next) {
+            cell.x = cell.x + step.x;
+            t_max.x = t_max.x + t_delta.x;
+        } else if (t_max.y == t_next) {
+            cell.y = cell.y + step.y;
+            t_max.y = t_max.y + t_delta.y;
+        } else {
+            cell.z = cell.z + step.z;
+            t_max.z = t_max.z + t_delta.z;
+        }
+        if (any(cell < vec3<i32>(0)) || any(cell >= vec3<i32>(i32(w)))) {
+            break;
+        }
+    }
+
+    // Premultiplied, blended over the background
+    return vec4<f32>(color, 1.0 - transmittance);
+}
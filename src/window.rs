@@ -7,9 +7,9 @@ use winit::{
     window::{Fullscreen, WindowBuilder},
 };
 
-use crate::{core::State, scene::Scene};
+use crate::{core::State, scene::Scene, settings::Settings};
 
-pub fn run(fullscreen: bool, fps: bool, grid_width: u32) {
+pub fn run(fullscreen: bool, fps: bool, settings: Settings) -> anyhow::Result<()> {
     env_logger::init();
     let event_loop = EventLoop::new();
     let mut window_builder = WindowBuilder::new();
@@ -26,8 +26,14 @@ pub fn run(fullscreen: bool, fps: bool, grid_width: u32) {
     let _scene = Scene::new_tube();
 
     // State::new uses async code, so we're going to wait for it to finish
-    let mut state = pollster::block_on(State::new(&window, None, grid_width));
+    let mut state = pollster::block_on(State::new(
+        Some(&window),
+        window.inner_size(),
+        None,
+        settings,
+    ))?;
 
+    let mut cursor_grabbed = false;
     let mut frame_count = 0;
     let mut accum_time = 0.;
     let mut last_frame_inst = Instant::now();
@@ -36,6 +42,7 @@ pub fn run(fullscreen: bool, fps: bool, grid_width: u32) {
     event_loop.run(move |event, _, control_flow| match event {
         Event::RedrawRequested(window_id) if window_id == window.id() => {
             state.update();
+            state.run_gui(&window);
             match pollster::block_on(state.render()) {
                 Ok(_) => {}
                 // Reconfigure the surface if lost
@@ -45,6 +52,7 @@ pub fn run(fullscreen: bool, fps: bool, grid_width: u32) {
                 // All other errors (Outdated, Timeout) should be resolved by the next frame
                 Err(e) => eprintln!("{:?}", e),
             }
+            pollster::block_on(state.capture());
 
             // Measure framerate
             if fps {
@@ -61,7 +69,20 @@ pub fn run(fullscreen: bool, fps: bool, grid_width: u32) {
                 }
             }
         }
+        Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } => state.mouse_motion(delta),
         Event::MainEventsCleared => {
+            if state.grabs_cursor() != cursor_grabbed {
+                cursor_grabbed = state.grabs_cursor();
+                // Not every platform can grab the cursor, mouse look still works without it
+                if let Err(e) = window.set_cursor_grab(cursor_grabbed) {
+                    eprintln!("{:?}", e);
+                }
+                window.set_cursor_visible(!cursor_grabbed);
+            }
+
             // RedrawRequested will only trigger once, unless we manually
             // request it.
             window.request_redraw();
@@ -69,29 +90,28 @@ pub fn run(fullscreen: bool, fps: bool, grid_width: u32) {
         Event::WindowEvent {
             ref event,
             window_id,
-        } if window_id == window.id() => {
-            if !state.input(event) {
-                match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
+        } if window_id == window.id() && !state.input(event) => match event {
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
                         ..
-                    } => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(physical_size) => {
-                        state.resize(*physical_size);
-                    }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        state.resize(**new_inner_size);
-                    }
-                    _ => {}
-                }
+                    },
+                ..
+            } => {
+                state.stop_recording();
+                *control_flow = ControlFlow::Exit
             }
-        }
+            WindowEvent::Resized(physical_size) => {
+                state.resize(*physical_size);
+            }
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                state.resize(**new_inner_size);
+            }
+            _ => {}
+        },
         _ => {}
     });
 }